@@ -0,0 +1,217 @@
+//! Map of bounded size that forgets its oldest entries
+//!
+//! The map holds two generations of entries: when the current one is full
+//! it becomes the old one and the older entries are dropped, so the size
+//! stays bounded and recently used entries stay. The generations are split
+//! into shards by hash, so that parallel users rarely wait for a lock. A
+//! small map has fewer shards, every shard holds at least one entry and all
+//! of them together never more than the capacity.
+//!
+//! The fitness cache of `ge` and the transposition table of `solve` are
+//! such maps.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+const SHARDS: usize = 16;
+
+#[derive(Debug)]
+struct Generations<K, V> {
+    current: HashMap<K, V>,
+    old: HashMap<K, V>,
+}
+
+impl<K, V> Default for Generations<K, V> {
+    fn default() -> Self {
+        Generations {
+            current: HashMap::new(),
+            old: HashMap::new(),
+        }
+    }
+}
+
+/// Bounded map with hit statistics
+#[derive(Debug)]
+pub struct BoundedMap<K, V> {
+    capacity: usize,
+    /// entries per generation of a shard, 0 if a shard has one generation only
+    generation: usize,
+    shards: Vec<Mutex<Generations<K, V>>>,
+    hits: AtomicU64,
+    lookups: AtomicU64,
+}
+
+impl<K, V> BoundedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    /// A map of at most `capacity` entries, 0 stores nothing
+    pub fn new(capacity: usize) -> Self {
+        let shards = (capacity / 2).clamp(1, SHARDS);
+        BoundedMap {
+            capacity,
+            generation: capacity / shards / 2,
+            shards: (0..shards)
+                .map(|_| Mutex::new(Generations::default()))
+                .collect(),
+            hits: AtomicU64::new(0),
+            lookups: AtomicU64::new(0),
+        }
+    }
+
+    fn shard(&self, key: &K) -> &Mutex<Generations<K, V>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    /// The value of a key, counted as lookup and hit
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        let mut generations = self.shard(key).lock().unwrap();
+        let value = match generations.current.get(key) {
+            Some(value) => value.clone(),
+            None => {
+                let value = generations.old.get(key)?.clone();
+                // keep a recent entry of the old generation while the current one has room
+                if generations.current.len() < self.generation {
+                    generations.old.remove(key);
+                    generations.current.insert(key.clone(), value.clone());
+                }
+                value
+            }
+        };
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(value)
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut generations = self.shard(&key).lock().unwrap();
+        if self.generation == 0 {
+            // a shard of the smallest maps holds a single entry
+            generations.current.clear();
+        } else if generations.current.len() >= self.generation {
+            generations.old = std::mem::take(&mut generations.current);
+        }
+        generations.current.insert(key, value);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn lookups(&self) -> u64 {
+        self.lookups.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                let generations = shard.lock().unwrap();
+                generations.current.len() + generations.old.len()
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The hits, lookups and size of this moment
+    pub fn usage(&self) -> Usage {
+        Usage {
+            hits: self.hits(),
+            lookups: self.lookups(),
+            len: self.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    /// Print the hits and the size, nothing if the map stores nothing
+    pub fn report(&self, name: &str, entries: &str) {
+        self.usage().report(name, entries);
+    }
+}
+
+/// Hit statistics and size of one or more maps
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Usage {
+    pub hits: u64,
+    pub lookups: u64,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl Usage {
+    /// Add the statistics of another map
+    pub fn merge(&mut self, other: &Usage) {
+        self.hits += other.hits;
+        self.lookups += other.lookups;
+        self.len += other.len;
+        self.capacity += other.capacity;
+    }
+
+    /// Print the hits and the size, nothing if the maps store nothing
+    pub fn report(&self, name: &str, entries: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        println!(
+            "{}: {} hits of {} lookups ({:.1}%), {} of {} {} stored",
+            name,
+            self.hits,
+            self.lookups,
+            100.0 * self.hits as f64 / self.lookups.max(1) as f64,
+            self.len,
+            self.capacity,
+            entries
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_and_generations() {
+        let map = BoundedMap::new(4 * SHARDS);
+        assert_eq!(map.get(&1), None);
+        map.insert(1, 'a');
+        assert_eq!(map.get(&1), Some('a'));
+        assert_eq!((map.hits(), map.lookups()), (1, 2));
+        for key in 2..1000 {
+            map.insert(key, 'b');
+        }
+        assert!(map.len() <= 4 * SHARDS);
+        // the latest entries are kept
+        assert_eq!(map.get(&999), Some('b'));
+    }
+
+    #[test]
+    fn test_small_capacity_bound() {
+        for capacity in [0, 1, 2, 3, 10, 33] {
+            let map = BoundedMap::new(capacity);
+            for key in 0..200 {
+                map.insert(key, ());
+                map.get(&(key / 2));
+                assert!(map.len() <= capacity);
+            }
+            assert_eq!(map.is_empty(), capacity == 0);
+        }
+        let map: BoundedMap<usize, ()> = BoundedMap::new(10);
+        assert_eq!((map.shards.len(), map.generation), (5, 1));
+    }
+}
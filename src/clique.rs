@@ -0,0 +1,330 @@
+//! The packing problem as maximum clique of the placement compatibility graph
+//!
+//! The vertices are the valid placements, an edge joins two placements of
+//! different pieces that do not overlap. A clique is a partial packing and
+//! a complete packing is a clique of PIECES vertices, one per piece.
+//!
+//! The graph is written in the DIMACS graph format with the placements of
+//! the vertices as comments. `solve` searches the maximum clique like
+//! Tomita's MCQ: the candidates are colored greedily, the vertices are
+//! tried in the reverse order of their colors and a vertex whose color
+//! cannot extend the clique beyond the best one ends the branch. The
+//! placements of a piece are independent, so the number of pieces among
+//! the candidates bounds the clique as well.
+
+use std::fmt::Write;
+use std::fs;
+use std::time::Instant;
+
+use crate::limits::Limits;
+use crate::piece::{PIECES, SHAPE_POINT};
+use crate::{i2c, placements};
+
+/// Bits of a word of a vertex set
+const BITS: usize = 64;
+
+/// Set of vertices as bits
+#[derive(Clone, Debug, PartialEq)]
+struct VertexSet(Vec<u64>);
+
+impl VertexSet {
+    fn new(vertices: usize) -> Self {
+        VertexSet(vec![0; vertices.div_ceil(BITS)])
+    }
+
+    fn insert(&mut self, v: usize) {
+        self.0[v / BITS] |= 1 << (v % BITS);
+    }
+
+    fn remove(&mut self, v: usize) {
+        self.0[v / BITS] &= !(1 << (v % BITS));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    /// The lowest vertex
+    fn first(&self) -> Option<usize> {
+        let index = self.0.iter().position(|word| *word != 0)?;
+        Some(index * BITS + self.0[index].trailing_zeros() as usize)
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn intersection(&self, other: &VertexSet) -> VertexSet {
+        VertexSet(self.0.iter().zip(&other.0).map(|(a, b)| a & b).collect())
+    }
+
+    /// The vertices in ascending order
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(index, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(index * BITS + bit)
+            })
+        })
+    }
+}
+
+/// The compatibility graph of a set of placement ids
+#[derive(Clone, Debug)]
+pub struct Graph {
+    /// placement id of the vertices, by descending degree
+    ids: Vec<usize>,
+    neighbours: Vec<VertexSet>,
+    edges: usize,
+}
+
+impl Graph {
+    /// The graph of the placement ids
+    fn new(ids: Vec<usize>) -> Self {
+        let compatible = |a: usize, b: usize| {
+            let (a, b) = (placements::entry(a), placements::entry(b));
+            a.name != b.name && a.mask & b.mask == 0
+        };
+        let degree = |a: usize| ids.iter().filter(|b| compatible(a, **b)).count();
+        let mut ids = ids.clone();
+        ids.sort_by_cached_key(|id| std::cmp::Reverse(degree(*id)));
+        let mut neighbours = vec![VertexSet::new(ids.len()); ids.len()];
+        let mut edges = 0;
+        for u in 0..ids.len() {
+            for v in u + 1..ids.len() {
+                if compatible(ids[u], ids[v]) {
+                    neighbours[u].insert(v);
+                    neighbours[v].insert(u);
+                    edges += 1;
+                }
+            }
+        }
+        Graph {
+            ids,
+            neighbours,
+            edges,
+        }
+    }
+
+    /// The graph of all valid placements
+    pub fn packing() -> Self {
+        Graph::new((0..placements::entries().len()).collect())
+    }
+
+    /// The graph in the DIMACS graph format, vertices count from 1
+    pub fn dimacs(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "c p3d placement compatibility graph of the 5x5x5 box").unwrap();
+        for (vertex, id) in (1..).zip(self.ids.iter()) {
+            let entry = placements::entry(*id);
+            writeln!(
+                text,
+                "c vertex {} {}:{}",
+                vertex,
+                i2c(entry.name as isize),
+                entry.combination
+            )
+            .unwrap();
+        }
+        writeln!(text, "p edge {} {}", self.ids.len(), self.edges).unwrap();
+        for (u, neighbours) in self.neighbours.iter().enumerate() {
+            for v in neighbours.iter().filter(|v| *v > u) {
+                writeln!(text, "e {} {}", u + 1, v + 1).unwrap();
+            }
+        }
+        text
+    }
+}
+
+/// Branch and bound for the maximum clique
+struct Search<'a> {
+    graph: &'a Graph,
+    limits: &'a Limits,
+    started: Instant,
+    nodes: u64,
+    clique: Vec<usize>,
+    best: Vec<usize>,
+    stop_reason: Option<String>,
+}
+
+impl<'a> Search<'a> {
+    fn limit_reached(&mut self) -> bool {
+        if self.stop_reason.is_none() {
+            self.stop_reason = self.limits.exceeded(self.nodes, self.started);
+        }
+        self.stop_reason.is_some()
+    }
+
+    /// The candidates in ascending order of greedy colors and their bounds
+    fn color(&self, candidates: &VertexSet) -> (Vec<usize>, Vec<usize>) {
+        let mut pieces = [false; PIECES];
+        for v in candidates.iter() {
+            pieces[placements::entry(self.graph.ids[v]).name] = true;
+        }
+        let pieces = pieces.iter().filter(|p| **p).count();
+        let mut order = Vec::with_capacity(candidates.len());
+        let mut bounds = Vec::with_capacity(candidates.len());
+        let mut uncolored = candidates.clone();
+        let mut color = 0;
+        while !uncolored.is_empty() {
+            color += 1;
+            let mut class = uncolored.clone();
+            while let Some(v) = class.first() {
+                class.remove(v);
+                uncolored.remove(v);
+                for (word, neighbours) in class.0.iter_mut().zip(&self.graph.neighbours[v].0) {
+                    *word &= !neighbours;
+                }
+                order.push(v);
+                bounds.push(color.min(pieces));
+            }
+        }
+        (order, bounds)
+    }
+
+    fn expand(&mut self, mut candidates: VertexSet) {
+        self.nodes += 1;
+        if self.limit_reached() {
+            return;
+        }
+        let (order, bounds) = self.color(&candidates);
+        for (v, bound) in order.iter().zip(bounds.iter()).rev() {
+            if self.clique.len() + bound <= self.best.len() {
+                return;
+            }
+            self.clique.push(*v);
+            let next = candidates.intersection(&self.graph.neighbours[*v]);
+            if next.is_empty() {
+                if self.clique.len() > self.best.len() {
+                    self.improved();
+                }
+            } else {
+                self.expand(next);
+            }
+            self.clique.pop();
+            candidates.remove(*v);
+            if self.stop_reason.is_some() {
+                return;
+            }
+        }
+    }
+
+    fn improved(&mut self) {
+        self.best = self.clique.clone();
+        println!(
+            "Node {}: clique of {} placements",
+            self.nodes,
+            self.best.len()
+        );
+        if self.best.len() == PIECES {
+            self.stop_reason = Some("complete packing found".to_string());
+        }
+    }
+}
+
+/// The maximum clique as placement ids, the stop reason and the nodes
+fn search(graph: &Graph, limits: &Limits) -> (Vec<usize>, String, u64) {
+    let mut search = Search {
+        graph,
+        limits,
+        started: Instant::now(),
+        nodes: 0,
+        clique: Vec::new(),
+        best: Vec::new(),
+        stop_reason: None,
+    };
+    let mut candidates = VertexSet::new(graph.ids.len());
+    for v in 0..graph.ids.len() {
+        candidates.insert(v);
+    }
+    search.expand(candidates);
+    let best = search.best.iter().map(|v| graph.ids[*v]).collect();
+    let stop_reason = search
+        .stop_reason
+        .unwrap_or_else(|| "search space exhausted".to_string());
+    (best, stop_reason, search.nodes)
+}
+
+/// Save the DIMACS graph or print it if there is nothing to solve
+pub fn export(graph: &Graph, file: Option<&str>, solve: bool) -> Result<(), String> {
+    match file {
+        None if !solve => print!("{}", graph.dimacs()),
+        None => {}
+        Some(file) => {
+            fs::write(file, graph.dimacs()).map_err(|e| format!("{}: {}", file, e))?;
+            println!(
+                "Graph with {} vertices and {} edges saved to {}",
+                graph.ids.len(),
+                graph.edges,
+                file
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Search the maximum clique and print its packing with the statistics
+pub fn solve(graph: &Graph, limits: &Limits) {
+    let started = Instant::now();
+    let (best, stop_reason, nodes) = search(graph, limits);
+    let b = placements::printbox(&best);
+    println!(
+        "Clique of {} placements, {} cells: {}",
+        best.len(),
+        best.len() * SHAPE_POINT,
+        b
+    );
+    println!("Stop criterion: {}", stop_reason);
+    println!(
+        "Clique: nodes: {}, placements: {}, runtime: {:.3}s",
+        nodes,
+        best.len(),
+        started.elapsed().as_secs_f64()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph() {
+        let ids: Vec<usize> = placements::range(0).chain(placements::range(1)).collect();
+        let graph = Graph::new(ids.clone());
+        let mut edges = 0;
+        for a in placements::range(0) {
+            for b in placements::range(1) {
+                if placements::entry(a).mask & placements::entry(b).mask == 0 {
+                    edges += 1;
+                }
+            }
+        }
+        assert_eq!(graph.edges, edges);
+        let degrees: Vec<usize> = graph.neighbours.iter().map(VertexSet::len).collect();
+        assert!(degrees.windows(2).all(|d| d[0] >= d[1]));
+        let text = graph.dimacs();
+        assert!(text.contains(&format!("\np edge {} {}\n", ids.len(), edges)));
+        assert_eq!(text.lines().filter(|l| l.starts_with("e ")).count(), edges);
+    }
+
+    #[test]
+    fn test_maximum_clique() {
+        let ids: Vec<usize> = placements::range(0)
+            .chain(placements::range(1))
+            .chain(placements::range(24))
+            .collect();
+        let (best, stop_reason, _) = search(&Graph::new(ids), &Limits::default());
+        assert_eq!(best.len(), 3);
+        assert_eq!(stop_reason, "search space exhausted");
+        let masks: Vec<u128> = best.iter().map(|id| placements::entry(*id).mask).collect();
+        assert_eq!(
+            masks.iter().fold(0, |all, mask| all | mask).count_ones(),
+            15
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! The packing problem as exact cover matrix for dancing links solvers
+//!
+//! The columns are the piece names and the cells, named by their `xyz`
+//! coordinates like `024`; the rows are the valid placements. The text
+//! format is the one of Knuth's DLX programs: the first line lists the
+//! primary columns, then `|` and the secondary columns that are covered at
+//! most once, and every further line lists the columns of a row. Lines that
+//! start with `|` are comments. The row legend maps the row numbers back to
+//! `name:combination` and the cells, as comments before the matrix or as a
+//! file of its own.
+
+use std::fmt::Write;
+use std::fs;
+use std::str::FromStr;
+
+use crate::cube::{cell_name, CELLS};
+use crate::piece::PIECES;
+use crate::{i2c, placements};
+
+/// Columns that are covered at most once instead of exactly once
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Secondary {
+    None,
+    Cells,
+    Pieces,
+}
+
+impl FromStr for Secondary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Secondary::None),
+            "cells" => Ok(Secondary::Cells),
+            "pieces" => Ok(Secondary::Pieces),
+            _ => Err(format!("Unknown secondary columns {}", s)),
+        }
+    }
+}
+
+/// The columns of a placement id, the piece first
+fn columns(id: usize) -> Vec<String> {
+    let entry = placements::entry(id);
+    let cells = (0..CELLS)
+        .filter(|cell| entry.mask & 1 << cell != 0)
+        .map(cell_name);
+    std::iter::once(i2c(entry.name as isize).to_string())
+        .chain(cells)
+        .collect()
+}
+
+/// The legend line of a row, numbered from 1
+fn legend_line(row: usize, id: usize) -> String {
+    let entry = placements::entry(id);
+    format!(
+        "{} {}:{} {}",
+        row,
+        i2c(entry.name as isize),
+        entry.combination,
+        columns(id)[1..].join(" ")
+    )
+}
+
+/// The matrix, with the legend as comments if `legend` is set
+pub fn matrix(secondary: Secondary, legend: bool) -> String {
+    let mut text = String::new();
+    let ids = 0..placements::entries().len();
+    writeln!(
+        text,
+        "| p3d packing of the 5x5x5 box: {} rows, {} columns",
+        ids.len(),
+        PIECES + CELLS
+    )
+    .unwrap();
+    if legend {
+        writeln!(text, "| row name:combination cells").unwrap();
+        for (row, id) in (1..).zip(ids.clone()) {
+            writeln!(text, "| {}", legend_line(row, id)).unwrap();
+        }
+    }
+    let pieces: Vec<String> = (0..PIECES).map(|n| i2c(n as isize).to_string()).collect();
+    let cells: Vec<String> = (0..CELLS).map(cell_name).collect();
+    let (primary, secondary) = match secondary {
+        Secondary::None => ([pieces, cells].concat(), Vec::new()),
+        Secondary::Cells => (pieces, cells),
+        Secondary::Pieces => (cells, pieces),
+    };
+    text.push_str(&primary.join(" "));
+    if !secondary.is_empty() {
+        write!(text, " | {}", secondary.join(" ")).unwrap();
+    }
+    writeln!(text).unwrap();
+    for id in ids {
+        writeln!(text, "{}", columns(id).join(" ")).unwrap();
+    }
+    text
+}
+
+/// The row legend as file of its own
+pub fn legend() -> String {
+    let mut text = String::from("# row name:combination cells\n");
+    for (row, id) in (1..).zip(0..placements::entries().len()) {
+        writeln!(text, "{}", legend_line(row, id)).unwrap();
+    }
+    text
+}
+
+/// Write the matrix and the legend or print them
+///
+/// Without a legend file the legend is part of the matrix as comments.
+pub fn export(
+    file: Option<&str>,
+    legend_file: Option<&str>,
+    secondary: Secondary,
+) -> Result<(), String> {
+    let text = matrix(secondary, legend_file.is_none());
+    match file {
+        None => print!("{}", text),
+        Some(file) => {
+            fs::write(file, text).map_err(|e| format!("{}: {}", file, e))?;
+            println!(
+                "Exact cover matrix with {} rows and {} columns saved to {}",
+                placements::entries().len(),
+                PIECES + CELLS,
+                file
+            );
+        }
+    }
+    if let Some(file) = legend_file {
+        fs::write(file, legend()).map_err(|e| format!("{}: {}", file, e))?;
+        println!("Row legend saved to {}", file);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matrix() {
+        let text = matrix(Secondary::None, false);
+        let lines: Vec<&str> = text.lines().filter(|l| !l.starts_with('|')).collect();
+        assert_eq!(lines.len(), 1 + placements::entries().len());
+        assert_eq!(lines[0].split(' ').count(), PIECES + CELLS);
+        assert!(lines[0].starts_with("a b c "));
+        assert!(lines[0].ends_with(" 443 444"));
+        assert!(lines[1..].iter().all(|l| l.split(' ').count() == 6));
+        let text = matrix(Secondary::Cells, false);
+        let header = text.lines().nth(1).unwrap();
+        assert!(header.starts_with("a b "));
+        assert!(header.contains(" y | 000 001 "));
+    }
+
+    #[test]
+    fn test_legend() {
+        let id = placements::id(7, 4195).unwrap();
+        let line = legend_line(id + 1, id);
+        assert!(line.starts_with(&format!("{} h:4195 ", id + 1)));
+        assert_eq!(
+            line.split(' ').skip(2).collect::<Vec<_>>(),
+            columns(id)[1..]
+        );
+        let inline = matrix(Secondary::None, true);
+        assert!(inline.contains(&format!("\n| {}\n", line)));
+        assert_eq!(legend().lines().nth(id + 1), Some(line.as_str()));
+    }
+}
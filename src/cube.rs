@@ -5,14 +5,64 @@
 pub const LENGTH: usize = 5;
 pub const WIDTH: usize = 5;
 pub const HEIGHT: usize = 5;
+/// Number of cells of the box
+pub const CELLS: usize = LENGTH * WIDTH * HEIGHT;
 const MIN_VAL: isize = 0;
 const MAX_VAL: isize = 24;
 
+/// Number of symmetries of the cube: 6 axis permutations times 8 reflections
+pub const SYMMETRIES: usize = 48;
+
+const AXIS_PERMUTATIONS: [[usize; 3]; 6] = [
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 0, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+/// Map a position by one of the symmetries of the cube
+///
+/// symmetry 0 is the identity
+pub fn symmetric(symmetry: usize, position: [isize; 3]) -> [isize; 3] {
+    let axes = AXIS_PERMUTATIONS[symmetry / 8];
+    let mut mapped = [0; 3];
+    for (i, axis) in axes.iter().enumerate() {
+        mapped[i] = if symmetry & (1 << i) != 0 {
+            LENGTH as isize - 1 - position[*axis]
+        } else {
+            position[*axis]
+        };
+    }
+    mapped
+}
+
+/// The cell number of a position, the bit of the cell in `Piece::cell_mask`
+pub fn cell(position: [isize; 3]) -> usize {
+    position[0] as usize * WIDTH * HEIGHT + position[1] as usize * HEIGHT + position[2] as usize
+}
+
+/// The position `[x, y, z]` of a cell number
+pub fn position(cell: usize) -> [usize; 3] {
+    [
+        cell / (WIDTH * HEIGHT),
+        cell / HEIGHT % WIDTH,
+        cell % HEIGHT,
+    ]
+}
+
+/// The name of a cell for messages and column names, `xyz`
+pub fn cell_name(cell: usize) -> String {
+    let [x, y, z] = position(cell);
+    format!("{}{}{}", x, y, z)
+}
+
 pub trait AddToBox {
     fn add(&mut self, x: usize, y: usize, z: usize, val: usize);
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PrintBox {
     value: [[[isize; LENGTH]; WIDTH]; HEIGHT],
 }
@@ -22,21 +72,17 @@ use std::fmt;
 impl fmt::Display for PrintBox {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // something else here
-        write!(f, "\n")?; // empty line
+        writeln!(f)?; // empty line
         for i in 0..LENGTH {
             for j in 0..WIDTH {
                 for k in 0..HEIGHT {
-                    // explicit way to demonstrate ? would solve
-                    match write!(f, "{}", super::i2c(self.value[i][j][k])) {
-                        Err(why) => return Err(why),
-                        Ok(file) => file,
-                    }
+                    write!(f, "{}", super::i2c(self.value[i][j][k]))?;
                 }
                 write!(f, " ")?;
             }
-            write!(f, "\n")?; // empty line
+            writeln!(f)?; // empty line
         }
-        write!(f, "\n")
+        writeln!(f)
     }
 }
 
@@ -57,6 +103,12 @@ impl AddToBox for PrintBox {
     }
 }
 
+impl Default for PrintBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PrintBox {
     pub fn new() -> PrintBox {
         PrintBox {
@@ -64,15 +116,33 @@ impl PrintBox {
         }
     }
 
-    /// determine how many positions are occupied
+    /// Add a value at every cell of a bit set of cells
+    pub fn add_mask(&mut self, mask: u128, val: usize) {
+        for cell in (0..CELLS).filter(|cell| mask & 1 << cell != 0) {
+            let [x, y, z] = position(cell);
+            self.add(x, y, z, val);
+        }
+    }
+
+    /// Remove a value from a position
     ///
-    /// returns the a number in range of 0..125
+    /// The position is empty afterwards if it held val,
+    /// an overlap or another value stays.
+    pub fn remove(&mut self, x: usize, y: usize, z: usize, val: usize) {
+        if self.value[x][y][z] == val as isize {
+            self.value[x][y][z] = MIN_VAL - 1;
+        }
+    }
+
+    /// determine how many positions are occupied by exactly one piece
+    ///
+    /// returns the a number in range of 0..=125, overlaps do not count
     pub fn occupied_positions(self) -> usize {
         let mut count: usize = 0;
         for x in 0..5 {
             for y in 0..5 {
                 for z in 0..5 {
-                    if self.value[x][y][z] > MIN_VAL && self.value[x][y][z] < MAX_VAL {
+                    if self.value[x][y][z] >= MIN_VAL && self.value[x][y][z] <= MAX_VAL {
                         count += 1;
                     }
                 }
@@ -86,6 +156,60 @@ impl PrintBox {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_symmetric() {
+        assert_eq!(symmetric(0, [1, 2, 3]), [1, 2, 3]);
+        assert_eq!(symmetric(1, [1, 2, 3]), [3, 2, 3]);
+        assert_eq!(symmetric(47, [1, 2, 3]), [1, 2, 3]);
+        // all symmetries are distinct
+        let mut images: Vec<Vec<[isize; 3]>> = (0..SYMMETRIES)
+            .map(|s| {
+                vec![
+                    symmetric(s, [0, 0, 0]),
+                    symmetric(s, [1, 0, 0]),
+                    symmetric(s, [0, 1, 0]),
+                ]
+            })
+            .collect();
+        images.sort();
+        images.dedup();
+        assert_eq!(images.len(), SYMMETRIES);
+    }
+
+    #[test]
+    fn test_cell_positions() {
+        for c in 0..CELLS {
+            let [x, y, z] = position(c);
+            assert_eq!(cell([x as isize, y as isize, z as isize]), c);
+        }
+        assert_eq!(position(62), [2, 2, 2]);
+        assert_eq!(cell_name(31), "111");
+        let mut b = PrintBox::new();
+        b.add_mask(1 << 62 | 1, 7);
+        assert_eq!(b.value[2][2][2], 7);
+        assert_eq!(b.value[0][0][0], 7);
+    }
+
+    #[test]
+    fn test_occupied_positions() {
+        let mut b = PrintBox::new();
+        assert_eq!(b.clone().occupied_positions(), 0);
+        // the first and the last piece count like the others
+        b.add(0, 0, 0, 0);
+        b.add(4, 4, 4, 24);
+        b.add(2, 2, 2, 7);
+        assert_eq!(b.clone().occupied_positions(), 3);
+        // an overlap does not count
+        b.add(2, 2, 2, 8);
+        assert_eq!(b.clone().occupied_positions(), 2);
+        let mut full = PrintBox::new();
+        for c in 0..CELLS {
+            let [x, y, z] = position(c);
+            full.add(x, y, z, c % 25);
+        }
+        assert_eq!(full.occupied_positions(), CELLS);
+    }
+
     #[test]
     fn test_create_print_box() {
         assert_eq!(
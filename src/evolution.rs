@@ -1,4 +1,16 @@
-use genevo::{operator::prelude::*, population::*, prelude::*, random::Rng, types::fmt::Display};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::Duration;
+use genevo::{
+    algorithm::BestSolution,
+    operator::prelude::*,
+    population::*,
+    prelude::*,
+    random::{random_index, random_seed, Rng, SeedableRng},
+    types::fmt::Display,
+};
 
 const NUM_INDIVIDUALS_PER_PARENTS: usize = 3;
 const SELECTION_RATIO: f64 = 0.7;
@@ -6,13 +18,54 @@ const MUTATION_RATE: f64 = 0.05;
 const MUTATION_PRECISION: u8 = 3;
 const REINSERTION_RATIO: f64 = 0.7;
 
+mod archive;
+mod cache;
+mod diversity;
+mod gene;
+mod island;
+mod nsga;
+mod permutation;
+mod pinning;
+mod repair;
+mod schedule;
+mod seeding;
+mod steering;
+mod sweep;
+mod termination;
+
+use self::archive::Archive;
+use self::cache::{CachedProblem, FitnessCache};
+pub use self::diversity::DiversityAction;
+use self::diversity::{diversity, DiversityControl};
+use self::gene::Gene;
+use self::island::{is_migration, Island};
+pub use self::permutation::{Decoder, Encoding, OrderCrossover, OrderMutation};
+use self::pinning::PinnedCrossBreeder;
+pub use self::pinning::Pins;
+use self::repair::RepairingMutator;
+pub use self::schedule::MutationSchedule;
+use self::schedule::RateSchedule;
+use self::steering::{Intervention, SteeredMutator, SteeredReinserter, Steering};
+pub use self::sweep::{parse_counts, parse_values, sweep, Sweep, TableFormat};
+use self::termination::{OptionalLimit, StagnationLimit};
+
+use super::bounded::Usage;
 use super::piece::*;
+use super::placements;
 
 /// The phenotype
-use super::cube::PrintBox;
+use super::cube::{AddToBox, PrintBox};
 
 /// The genotype
-type Placement = Vec<Piece>;
+type Placement = Vec<Gene>;
+
+/// The combination indices of all pieces of a placement
+fn genes(placement: &Placement) -> Vec<u16> {
+    placement
+        .iter()
+        .map(|gene| gene.get_combination())
+        .collect()
+}
 
 /// How do the genes of the genotype show up in the phenotype
 trait AsPhenotype {
@@ -22,8 +75,10 @@ trait AsPhenotype {
 impl AsPhenotype for Placement {
     fn as_printbox(&self) -> PrintBox {
         let mut b = PrintBox::new();
-        for p in self {
-            p.add_to_box(&mut b);
+        for gene in self {
+            for [x, y, z] in gene.get_cells() {
+                b.add(*x as usize, *y as usize, *z as usize, gene.get_name());
+            }
         }
         b
     }
@@ -52,120 +107,578 @@ impl FitnessFunction<Placement, usize> for Problem {
     }
 }
 
-impl BreederValueMutation for Piece {
-    fn breeder_mutated(value: Self, _other: &Piece, _adjustment: f64, _sign: i8) -> Self {
-        let mut p = value.clone();
-        p.next_config();
-        while !p.is_config() {
-            p.next_config();
+impl BreederValueMutation for Gene {
+    fn breeder_mutated(value: Self, _other: &Gene, _adjustment: f64, _sign: i8) -> Self {
+        if value.is_pinned() {
+            return value;
         }
-        p
+        value.next_valid()
     }
 }
 
-impl RandomValueMutation for Piece {
-    fn random_mutated<R>(value: Self, _min_value: &Piece, _max_value: &Piece, _rng: &mut R) -> Self
+impl RandomValueMutation for Gene {
+    fn random_mutated<R>(value: Self, _min_value: &Gene, _max_value: &Gene, _rng: &mut R) -> Self
     where
         R: Rng + Sized,
     {
-        let mut p = value.clone();
-        p.next_config();
-        while !p.is_config() {
-            p.next_config();
+        if value.is_pinned() {
+            return value;
         }
-        p
+        value.next_valid()
     }
 }
 
 /// Generate some random cubes with placement
-struct CubePacking;
+struct CubePacking {
+    pins: Pins,
+}
 
 impl GenomeBuilder<Placement> for CubePacking {
     fn build_genome<R>(&self, _: usize, _rng: &mut R) -> Placement
     where
         R: Rng + Sized,
     {
-        (0..PIECES)
-            .map(|index| {
-                let mut p = Piece::new(index);
-                while !p.is_config() {
-                    p.next_config();
-                }
-                p
-            })
-            .collect::<Vec<Piece>>()
+        let mut placement = (0..PIECES).map(Gene::first).collect::<Placement>();
+        self.pins.apply(&mut placement);
+        placement
+    }
+}
+
+/// Generate cubes with randomly chosen valid placements of the pieces
+#[derive(Clone, Debug)]
+struct RandomPacking {
+    pins: Pins,
+}
+
+impl RandomPacking {
+    fn new(pins: Pins) -> Self {
+        RandomPacking { pins }
+    }
+}
+
+impl GenomeBuilder<Placement> for RandomPacking {
+    fn build_genome<R>(&self, _: usize, rng: &mut R) -> Placement
+    where
+        R: Rng + Sized,
+    {
+        let mut placement = (0..PIECES)
+            .map(|name| Gene::nth(name, random_index(rng, placements::range(name).len())))
+            .collect::<Placement>();
+        self.pins.apply(&mut placement);
+        placement
+    }
+}
+
+/// Parameters of a genetic evolution run
+///
+/// The run ends as soon as one of the limits is reached.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// maximal number of generations
+    pub generations: u64,
+    /// number of individuals of the population
+    pub population: usize,
+    /// wall clock limit in seconds
+    pub time_limit: Option<u64>,
+    /// maximal number of generations without improvement of the best fitness
+    pub stagnation: Option<u64>,
+    /// fitness that is good enough, the highest possible fitness if not set
+    pub target_fitness: Option<usize>,
+    /// react if the population diversity drops below this value (0.0 .. 1.0)
+    pub diversity_threshold: Option<f64>,
+    /// the reaction on a low diversity
+    pub diversity_action: DiversityAction,
+    /// fraction of the population that is replaced on reseeding
+    pub reseed_ratio: f64,
+    /// factor the mutation rate is raised by on boosting
+    pub mutation_boost: f64,
+    /// number of the best distinct individuals kept on a restart
+    pub elite: usize,
+    /// minimal number of generations between two reactions
+    pub diversity_cooldown: u64,
+    /// probability per gene to mutate
+    pub mutation_rate: f64,
+    /// seed for reproducible runs, random if not set
+    pub seed: Option<u64>,
+    /// number of populations that evolve in parallel
+    pub islands: usize,
+    /// mutation rate per island, repeated if there are less rates than islands
+    pub island_mutation_rates: Vec<f64>,
+    /// number of generations between two migrations, 0 for none
+    pub migration_interval: u64,
+    /// number of the best individuals that migrate to the next island
+    pub migrants: usize,
+    /// number of the best distinct placements kept over the whole run
+    pub hall_of_fame: usize,
+    /// placements that are symmetric to each other count as one
+    pub modulo_symmetry: bool,
+    /// save the hall of fame to this file instead of printing it
+    pub hall_of_fame_file: Option<String>,
+    /// file with placements that are part of the initial population
+    pub seed_population: Option<String>,
+    /// pieces that stay at a fixed combination
+    pub pins: Pins,
+    /// genome of the evolution: combination indices or the order of the pieces
+    pub encoding: Encoding,
+    /// first fit decoder of the permutation encoding
+    pub decoder: Decoder,
+    /// crossover of the permutation encoding
+    pub order_crossover: OrderCrossover,
+    /// mutation of the permutation encoding
+    pub order_mutation: OrderMutation,
+    /// optimize covered cells, overlaps and anchor violations as separate objectives
+    pub nsga2: bool,
+    /// how the mutation rate changes over the run
+    pub mutation_schedule: MutationSchedule,
+    /// mutation rate at the generation limit of the decays, default a tenth of the initial rate
+    pub final_mutation_rate: Option<f64>,
+    /// no progress and result output of a single population run
+    pub quiet: bool,
+    /// number of placements whose fitness is cached, 0 turns the cache off
+    pub fitness_cache: usize,
+    /// threads that evaluate the fitness of a population, all cores if not set
+    pub threads: Option<usize>,
+    /// repair mutated genomes of the placement encoding by constraint propagation, not with NSGA-II
+    pub repair: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            generations: 1000,
+            population: 1000,
+            time_limit: None,
+            stagnation: None,
+            target_fitness: None,
+            diversity_threshold: None,
+            diversity_action: DiversityAction::Reseed,
+            reseed_ratio: 0.5,
+            mutation_boost: 4.0,
+            elite: 10,
+            diversity_cooldown: 10,
+            mutation_rate: MUTATION_RATE,
+            seed: None,
+            islands: 1,
+            island_mutation_rates: Vec::new(),
+            migration_interval: 25,
+            migrants: 5,
+            hall_of_fame: 0,
+            modulo_symmetry: false,
+            hall_of_fame_file: None,
+            seed_population: None,
+            pins: Pins::default(),
+            encoding: Encoding::Placement,
+            decoder: Decoder::Configs,
+            order_crossover: OrderCrossover::Ox,
+            order_mutation: OrderMutation::Swap,
+            nsga2: false,
+            mutation_schedule: MutationSchedule::Fixed,
+            final_mutation_rate: None,
+            quiet: false,
+            fitness_cache: 0,
+            threads: None,
+            repair: false,
+        }
+    }
+}
+
+/// The result of one evolution run
+#[derive(Clone, Debug)]
+struct Outcome {
+    /// best placement seen during the whole run
+    best: Placement,
+    fitness: usize,
+    /// generation the best placement was found in
+    generation: u64,
+    /// number of processed generations
+    generations: u64,
+    stop_reason: String,
+    /// best distinct placements of the whole run
+    hall_of_fame: Archive,
+    /// hits and size of the fitness cache at the end of the run
+    cache: Usage,
+}
+
+/// The seed of a run, derived from the user seed if there is one
+fn run_seed(seed: Option<u64>, run: usize) -> Seed {
+    match seed {
+        Some(seed) => Prng::seed_from_u64(seed.wrapping_add(run as u64)).gen(),
+        None => random_seed(),
+    }
+}
+
+/// Size the thread pool of the parallel fitness evaluation
+///
+/// genevo evaluates populations of 50 and more individuals on the global
+/// rayon pool, islands and sweep runs share it. The fitness does not depend
+/// on the evaluation order, so seeded runs give the same result on any
+/// number of threads.
+fn use_threads(threads: Option<usize>) {
+    if let Some(threads) = threads {
+        if let Err(error) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            println!("Cannot evaluate on {} threads: {}", threads, error);
+        }
+    }
+}
+
+pub fn solve_cube(settings: &Settings) {
+    use_threads(settings.threads);
+    println!(
+        "Fitness evaluation on {} threads",
+        rayon::current_num_threads()
+    );
+    if !settings.pins.is_empty() {
+        println!("Pinned pieces: {}", settings.pins);
+    }
+    if settings.repair && (settings.nsga2 || settings.encoding == Encoding::Permutation) {
+        println!("The repair needs the placement encoding and no NSGA-II");
+        return;
+    }
+    if settings.nsga2 {
+        if settings.islands > 1
+            || settings.diversity_threshold.is_some()
+            || settings.seed_population.is_some()
+            || settings.hall_of_fame > 0
+            || settings.fitness_cache > 0
+            || settings.encoding == Encoding::Permutation
+        {
+            println!(
+                "Islands, diversity control, seed population, the hall of fame, \
+                 the fitness cache and the permutation encoding do not work with NSGA-II"
+            );
+            return;
+        }
+        nsga::evolve(settings, run_seed(settings.seed, 0));
+        return;
+    }
+    if settings.encoding == Encoding::Permutation {
+        if settings.islands > 1
+            || settings.diversity_threshold.is_some()
+            || settings.seed_population.is_some()
+            || settings.mutation_schedule != MutationSchedule::Fixed
+            || settings.fitness_cache > 0
+        {
+            println!(
+                "Islands, diversity control, seed population, mutation schedules \
+                 and the fitness cache need the placement encoding"
+            );
+            return;
+        }
+        permutation::evolve(settings, run_seed(settings.seed, 0));
+        return;
+    }
+    let mut seeds = match &settings.seed_population {
+        None => Vec::new(),
+        Some(file) => match seeding::load(file) {
+            Ok(seeds) => {
+                println!("Seed population with {} placements", seeds.len());
+                seeds
+            }
+            Err(error) => {
+                println!("Cannot seed population: {}", error);
+                return;
+            }
+        },
+    };
+    for seed in seeds.iter_mut() {
+        settings.pins.apply(seed);
+    }
+    if settings.islands > 1 {
+        island::solve_islands(settings, &seeds);
+    } else {
+        evolve(settings, run_seed(settings.seed, 0), &seeds, None);
     }
 }
 
-pub fn solve_cube(generations: u64, population: usize) {
-    let initial_population: Population<Placement> = build_population()
-        .with_genome_builder(CubePacking)
-        .of_size(population)
-        .uniform_at_random();
+/// Run one population until a termination criterion is reached
+///
+/// Without island the progress is printed, otherwise it is reported to
+/// the island model.
+fn evolve(settings: &Settings, seed: Seed, seeds: &[Placement], island: Option<Island>) -> Outcome {
+    let target_fitness = settings
+        .target_fitness
+        .unwrap_or_else(|| Problem.highest_possible_fitness());
+    let mut individuals: Vec<Placement> = seeds.iter().take(settings.population).cloned().collect();
+    if individuals.len() < settings.population {
+        let built: Population<Placement> = build_population()
+            .with_genome_builder(CubePacking {
+                pins: settings.pins.clone(),
+            })
+            .of_size(settings.population - individuals.len())
+            .using_seed(seed);
+        individuals.extend_from_slice(built.individuals());
+    }
+    let initial_population = Population::with_individuals(individuals);
+    let steering = Steering::new(settings.mutation_rate);
+    let mut elite = Archive::new(settings.elite);
+    let hall_of_fame = if settings.modulo_symmetry {
+        Archive::modulo_symmetry(settings.hall_of_fame)
+    } else {
+        Archive::new(settings.hall_of_fame)
+    };
+    let mut control = DiversityControl::new(settings, settings.mutation_rate);
+    let mut schedule = RateSchedule::new(settings);
+    let cache = Arc::new(FitnessCache::new(settings.fitness_cache));
+    let problem = CachedProblem::new(cache.clone());
 
     let mut pack_sim = simulate(
         genetic_algorithm()
-            .with_evaluation(Problem)
+            .with_evaluation(problem.clone())
             .with_selection(RouletteWheelSelector::new(
                 SELECTION_RATIO,
                 NUM_INDIVIDUALS_PER_PARENTS,
             ))
-            .with_crossover(SinglePointCrossBreeder::new())
-            .with_mutation(BreederValueMutator::new(
-                MUTATION_RATE,
-                Piece::new(24), // Mutation Range Genotype::DNA
-                MUTATION_PRECISION,
-                Piece::new(0),  // Min Value
-                Piece::new(24), // Max Value
+            .with_crossover(PinnedCrossBreeder::new(settings.pins.clone()))
+            .with_mutation(RepairingMutator::new(
+                SteeredMutator::new(
+                    steering.clone(),
+                    Gene::highest(), // Mutation Range Genotype::DNA
+                    MUTATION_PRECISION,
+                    Gene::lowest(),  // Min Value
+                    Gene::highest(), // Max Value
+                ),
+                settings.repair,
+            ))
+            .with_reinsertion(SteeredReinserter::new(
+                ElitistReinserter::new(problem, false, REINSERTION_RATIO),
+                RandomPacking::new(settings.pins.clone()),
+                steering.clone(),
             ))
-            .with_reinsertion(ElitistReinserter::new(Problem, false, REINSERTION_RATIO))
             .with_initial_population(initial_population)
             .build(),
     )
     .until(or(
-        FitnessLimit::new(Problem.highest_possible_fitness()),
-        GenerationLimit::new(generations),
+        or(
+            FitnessLimit::new(target_fitness),
+            GenerationLimit::new(settings.generations),
+        ),
+        or(
+            OptionalLimit(
+                settings
+                    .time_limit
+                    .map(|seconds| TimeLimit::new(Duration::seconds(seconds as i64))),
+            ),
+            OptionalLimit(settings.stagnation.map(StagnationLimit::new)),
+        ),
     ))
-    .build();
+    .build_with_seed(seed);
 
+    let mut outcome = Outcome {
+        best: Vec::new(),
+        fitness: 0,
+        generation: 0,
+        generations: 0,
+        stop_reason: String::new(),
+        hall_of_fame,
+        cache: Usage::default(),
+    };
     loop {
         let result = pack_sim.step();
         match result {
             Ok(SimResult::Intermediate(step)) => {
                 let evaluated_population = step.result.evaluated_population;
                 let best_solution = step.result.best_solution;
-                println!(
-                    "Step: generation: {}, average_fitness: {}, \
-                     best fitness: {}, duration: {}, processing_time: {}",
-                    step.iteration,
-                    evaluated_population.average_fitness(),
-                    best_solution.solution.fitness,
-                    step.duration.fmt(),
-                    step.processing_time.fmt()
+                let individuals = evaluated_population.individuals();
+                let diversity = diversity(&individuals);
+                outcome.offer(&best_solution, step.iteration);
+                match &island {
+                    None if settings.quiet => {}
+                    None => println!(
+                        "Step: generation: {}, average_fitness: {}, \
+                         best fitness: {}, diversity: {:.3}, mutation rate: {:.4}, \
+                         duration: {}, processing_time: {}",
+                        step.iteration,
+                        evaluated_population.average_fitness(),
+                        best_solution.solution.fitness,
+                        diversity,
+                        steering.mutation_rate(),
+                        step.duration.fmt(),
+                        step.processing_time.fmt()
+                    ),
+                    Some(island) => island.report(
+                        step.iteration,
+                        best_solution.solution.fitness,
+                        *evaluated_population.average_fitness(),
+                        diversity,
+                        steering.mutation_rate(),
+                    ),
+                }
+                for (placement, fitness) in individuals
+                    .iter()
+                    .zip(evaluated_population.fitness_values())
+                {
+                    elite.offer(placement, *fitness);
+                    outcome.hall_of_fame.offer(placement, *fitness);
+                }
+                steering.set_scheduled_rate(
+                    schedule.next(step.iteration, best_solution.solution.fitness),
                 );
+                if let Some(response) = control.check(step.iteration, diversity) {
+                    if island.is_none() && !settings.quiet {
+                        println!("Diversity {:.3}: {:?}", diversity, response);
+                    }
+                    steering.react(response, || elite.placements());
+                }
+                if let Some(island) = &island {
+                    if is_migration(step.iteration, settings.migration_interval) {
+                        let migrants = island.migrate(best_of(
+                            &individuals,
+                            evaluated_population.fitness_values(),
+                            settings.migrants,
+                        ));
+                        steering.intervene(Intervention::Immigrate(migrants));
+                    }
+                    if island.is_solved() {
+                        outcome.generations = step.iteration;
+                        outcome.stop_reason =
+                            "Simulation stopped since another island reached the target fitness."
+                                .to_string();
+                        break;
+                    }
+                }
             }
             Ok(SimResult::Final(step, processing_time, duration, stop_reason)) => {
                 let best_solution = step.result.best_solution;
-                println!("{}", stop_reason);
-                println!(
-                    "Final result after {}: generation: {}, \
-                     best solution with fitness {} found in generation {}, processing_time: {}",
-                    duration.fmt(),
-                    step.iteration,
-                    best_solution.solution.fitness,
-                    best_solution.generation,
-                    processing_time.fmt()
-                );
-                println!(
-                    "Final Best: {}",
-                    best_solution.solution.genome.as_printbox()
-                );
+                outcome.offer(&best_solution, step.iteration);
+                outcome.generations = step.iteration;
+                outcome.stop_reason = stop_reason;
+                match &island {
+                    None if settings.quiet => {}
+                    None => {
+                        println!("Stop criterion: {}", outcome.stop_reason);
+                        println!(
+                            "Final result after {}: generation: {}, \
+                             best solution with fitness {} found in generation {}, \
+                             processing_time: {}",
+                            duration.fmt(),
+                            step.iteration,
+                            outcome.fitness,
+                            outcome.generation,
+                            processing_time.fmt()
+                        );
+                        println!("Final Best: {}", outcome.best.as_printbox());
+                        report_throughput(
+                            settings,
+                            &outcome.best,
+                            step.iteration,
+                            duration.num_milliseconds(),
+                        );
+                        cache.report("Fitness cache", "placements");
+                        report_hall_of_fame(settings, &outcome.hall_of_fame);
+                    }
+                    Some(island) => {
+                        island.report(
+                            step.iteration,
+                            best_solution.solution.fitness,
+                            *step.result.evaluated_population.average_fitness(),
+                            diversity(&step.result.evaluated_population.individuals()),
+                            steering.mutation_rate(),
+                        );
+                        if outcome.fitness >= target_fitness {
+                            island.solved();
+                        }
+                    }
+                }
                 break;
             }
             Err(error) => {
                 println!("{}", error);
+                outcome.stop_reason = error.to_string();
                 break;
             }
         }
     }
+    outcome.cache = cache.usage();
+    outcome
+}
+
+impl Outcome {
+    /// Keep the best solution of a generation if it is better than the known one
+    fn offer(&mut self, best_solution: &BestSolution<Placement, usize>, generation: u64) {
+        if self.best.is_empty() || best_solution.solution.fitness > self.fitness {
+            self.best = best_solution.solution.genome.clone();
+            self.fitness = best_solution.solution.fitness;
+            self.generation = generation;
+        }
+    }
+}
+
+/// Genomes cloned and evaluated per encoding for the throughput comparison
+const THROUGHPUT_SAMPLES: u32 = 2000;
+
+/// Seconds to clone and evaluate a genome of genes and of pieces
+///
+/// Genes are ids into the placement lookup table, a genome used to hold
+/// the full pieces with their cells.
+fn clone_and_evaluate(genome: &Placement) -> (f64, f64) {
+    let pieces: Vec<Piece> = genome.iter().map(|gene| gene.piece()).collect();
+    let started = Instant::now();
+    for _ in 0..THROUGHPUT_SAMPLES {
+        let copy = black_box(genome).clone();
+        black_box(Problem.fitness_of(&copy));
+    }
+    let genes = started.elapsed().as_secs_f64() / THROUGHPUT_SAMPLES as f64;
+    let started = Instant::now();
+    for _ in 0..THROUGHPUT_SAMPLES {
+        let copy = black_box(&pieces).clone();
+        let mut b = PrintBox::new();
+        for piece in &copy {
+            piece.add_to_box(&mut b);
+        }
+        black_box(b.occupied_positions());
+    }
+    let pieces = started.elapsed().as_secs_f64() / THROUGHPUT_SAMPLES as f64;
+    (genes, pieces)
+}
+
+/// Print the memory and the clone and evaluation time of a genome against the pieces
+/// and how many individuals were processed per second
+fn report_throughput(settings: &Settings, best: &Placement, generations: u64, milliseconds: i64) {
+    let genome = PIECES * std::mem::size_of::<Gene>();
+    let pieces = PIECES * std::mem::size_of::<Piece>();
+    let (genes_time, pieces_time) = clone_and_evaluate(best);
+    println!(
+        "Genome: {} bytes instead of {} bytes as pieces ({}x smaller), \
+         clone and evaluate: {:.2}µs instead of {:.2}µs as pieces ({:.2}x speedup), \
+         throughput: {:.0} individuals per second",
+        genome,
+        pieces,
+        pieces / genome,
+        genes_time * 1e6,
+        pieces_time * 1e6,
+        pieces_time / genes_time.max(f64::MIN_POSITIVE),
+        (settings.population as u64 * generations) as f64 * 1000.0 / milliseconds.max(1) as f64
+    );
+}
+
+/// Print the hall of fame or save it if there is a file
+fn report_hall_of_fame(settings: &Settings, hall_of_fame: &Archive) {
+    if settings.hall_of_fame == 0 {
+        return;
+    }
+    match &settings.hall_of_fame_file {
+        None => hall_of_fame.print(),
+        Some(file) => match hall_of_fame.save(file) {
+            Ok(()) => println!(
+                "Hall of fame with {} placements saved to {}",
+                hall_of_fame.len(),
+                file
+            ),
+            Err(error) => println!("Cannot save hall of fame to {}: {}", file, error),
+        },
+    }
+}
+
+/// The `count` fittest individuals of a population
+fn best_of(individuals: &[Placement], fitness: &[usize], count: usize) -> Vec<Placement> {
+    let mut ranking: Vec<usize> = (0..individuals.len()).collect();
+    ranking.sort_by(|a, b| fitness[*b].cmp(&fitness[*a]));
+    ranking
+        .iter()
+        .take(count)
+        .map(|index| individuals[*index].clone())
+        .collect()
 }
@@ -0,0 +1,195 @@
+//! Archive of the best distinct placements seen during a run
+//!
+//! Placements are distinct if at least one piece is placed differently.
+//! Optionally placements count as equal if a symmetry of the cube maps the
+//! pieces of one onto the pieces of the other. As the anchors of the
+//! pieces are not symmetric, the names of the pieces are ignored then.
+
+use std::fs;
+use std::io;
+
+use crate::cube::{self, symmetric, SYMMETRIES};
+
+use super::{genes, AsPhenotype, Placement};
+
+/// Keeps the best distinct placements, sorted from best to worst
+#[derive(Clone, Debug)]
+pub struct Archive {
+    capacity: usize,
+    modulo_symmetry: bool,
+    entries: Vec<(Placement, usize, Vec<u16>)>,
+}
+
+impl Archive {
+    pub fn new(capacity: usize) -> Self {
+        Archive {
+            capacity,
+            modulo_symmetry: false,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// An archive that treats symmetric placements as equal
+    pub fn modulo_symmetry(capacity: usize) -> Self {
+        Archive {
+            modulo_symmetry: true,
+            ..Archive::new(capacity)
+        }
+    }
+
+    /// Take a placement into the archive if it is good enough and not yet known
+    pub fn offer(&mut self, placement: &Placement, fitness: usize) {
+        if self.entries.len() == self.capacity
+            && self
+                .entries
+                .last()
+                .is_none_or(|(_, worst, _)| fitness <= *worst)
+        {
+            return;
+        }
+        let key = if self.modulo_symmetry {
+            symmetric_key(placement)
+        } else {
+            genes(placement)
+        };
+        if self.entries.iter().any(|(_, _, known)| *known == key) {
+            return;
+        }
+        let position = self
+            .entries
+            .iter()
+            .position(|(_, f, _)| fitness > *f)
+            .unwrap_or(self.entries.len());
+        self.entries
+            .insert(position, (placement.clone(), fitness, key));
+        self.entries.truncate(self.capacity);
+    }
+
+    /// Offer all entries of another archive
+    pub fn merge(&mut self, other: &Archive) {
+        for (placement, fitness) in other.entries() {
+            self.offer(placement, fitness);
+        }
+    }
+
+    /// The placements with their fitness from best to worst
+    pub fn entries(&self) -> impl Iterator<Item = (&Placement, usize)> {
+        self.entries.iter().map(|(p, f, _)| (p, *f))
+    }
+
+    pub fn placements(&self) -> Vec<Placement> {
+        self.entries.iter().map(|(p, _, _)| p.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn print(&self) {
+        for (rank, (placement, fitness)) in self.entries().enumerate() {
+            println!(
+                "Hall of fame #{} with fitness {}: {}",
+                rank + 1,
+                fitness,
+                placement.as_printbox()
+            );
+        }
+    }
+
+    /// Save the placements as combination indices, one placement per line
+    ///
+    /// Lines starting with '#' are comments.
+    pub fn save(&self, file: &str) -> io::Result<()> {
+        let mut content = String::new();
+        for (placement, fitness) in self.entries() {
+            content.push_str(&format!("# fitness {}\n", fitness));
+            let combinations: Vec<String> =
+                genes(placement).iter().map(|c| c.to_string()).collect();
+            content.push_str(&combinations.join(" "));
+            content.push('\n');
+        }
+        fs::write(file, content)
+    }
+}
+
+/// The smallest image of the placement under all symmetries of the cube
+///
+/// An image is the sorted list of the sorted cell numbers of the pieces.
+fn symmetric_key(placement: &Placement) -> Vec<u16> {
+    (0..SYMMETRIES)
+        .map(|symmetry| {
+            let mut pieces: Vec<[u16; 5]> = placement
+                .iter()
+                .map(|piece| {
+                    let mut cells = [0u16; 5];
+                    for (cell, position) in cells.iter_mut().zip(piece.get_cells().iter()) {
+                        *cell = cube::cell(symmetric(symmetry, *position)) as u16;
+                    }
+                    cells.sort_unstable();
+                    cells
+                })
+                .collect();
+            pieces.sort_unstable();
+            pieces.concat()
+        })
+        .min()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::gene::Gene;
+    use crate::piece::PIECES;
+
+    fn placement(choice: usize) -> Placement {
+        (0..PIECES).map(|name| Gene::nth(name, choice)).collect()
+    }
+
+    #[test]
+    fn test_archive_keeps_best_distinct() {
+        let mut archive = Archive::new(2);
+        archive.offer(&placement(0), 50);
+        archive.offer(&placement(0), 60);
+        archive.offer(&placement(1), 70);
+        archive.offer(&placement(2), 40);
+        let fitness: Vec<usize> = archive.entries().map(|(_, f)| f).collect();
+        assert_eq!(fitness, vec![70, 50]);
+    }
+
+    /// a piece that occupies the given cells
+    ///
+    /// All pieces have the same shape, the free piece 'y' fits everywhere.
+    fn piece_at(mut cells: Vec<[isize; 3]>) -> Gene {
+        cells.sort();
+        (0..960)
+            .map(|index| Gene::nth(24, index))
+            .find(|gene| {
+                let mut occupied = gene.get_cells().to_vec();
+                occupied.sort();
+                occupied == cells
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_symmetric_key_of_mirrored_placement() {
+        let original = placement(3);
+        // mirror at the plane x = 2
+        let mirrored: Placement = original
+            .iter()
+            .map(|piece| {
+                let cells = piece.get_cells().iter().map(|p| symmetric(1, *p)).collect();
+                piece_at(cells)
+            })
+            .collect();
+        assert_ne!(genes(&original), genes(&mirrored));
+        assert_eq!(symmetric_key(&original), symmetric_key(&mirrored));
+        assert_ne!(symmetric_key(&original), symmetric_key(&placement(4)));
+
+        let mut archive = Archive::modulo_symmetry(3);
+        archive.offer(&original, 50);
+        archive.offer(&mirrored, 60);
+        assert_eq!(archive.len(), 1);
+    }
+}
@@ -0,0 +1,83 @@
+//! Cache of fitness values of placements
+//!
+//! Elitist reinsertion evaluates the offspring that the next generation
+//! evaluates again, and the deterministic mutations breed many equal
+//! genomes. The cache is keyed by the genes, which are the combination
+//! indices as dense ids, and keeps the recent genomes within its capacity.
+
+use std::sync::Arc;
+
+use genevo::prelude::FitnessFunction;
+
+use crate::bounded::BoundedMap;
+
+use super::{Placement, Problem};
+
+/// Bounded cache with hit statistics
+pub type FitnessCache = BoundedMap<Placement, usize>;
+
+/// The fitness function of `Problem` with the cache in front
+#[derive(Clone, Debug)]
+pub struct CachedProblem {
+    cache: Arc<FitnessCache>,
+}
+
+impl CachedProblem {
+    pub fn new(cache: Arc<FitnessCache>) -> Self {
+        CachedProblem { cache }
+    }
+}
+
+impl FitnessFunction<Placement, usize> for CachedProblem {
+    fn fitness_of(&self, placement: &Placement) -> usize {
+        if self.cache.capacity() == 0 {
+            return Problem.fitness_of(placement);
+        }
+        if let Some(fitness) = self.cache.get(placement) {
+            return fitness;
+        }
+        let fitness = Problem.fitness_of(placement);
+        self.cache.insert(placement.clone(), fitness);
+        fitness
+    }
+
+    fn average(&self, values: &[usize]) -> usize {
+        Problem.average(values)
+    }
+
+    fn highest_possible_fitness(&self) -> usize {
+        Problem.highest_possible_fitness()
+    }
+
+    fn lowest_possible_fitness(&self) -> usize {
+        Problem.lowest_possible_fitness()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::gene::Gene;
+    use crate::piece::PIECES;
+
+    fn placement(choice: usize) -> Placement {
+        (0..PIECES).map(|name| Gene::nth(name, choice)).collect()
+    }
+
+    #[test]
+    fn test_cache_hits_and_bound() {
+        let cache = Arc::new(FitnessCache::new(10));
+        let problem = CachedProblem::new(cache.clone());
+        for choice in 0..3 {
+            let fitness = problem.fitness_of(&placement(choice));
+            assert_eq!(problem.fitness_of(&placement(choice)), fitness);
+            assert_eq!(fitness, Problem.fitness_of(&placement(choice)));
+        }
+        assert_eq!(cache.hits(), 3);
+        assert_eq!(cache.lookups(), 6);
+        for choice in 3..200 {
+            problem.fitness_of(&placement(choice));
+        }
+        assert!(cache.len() <= 10);
+    }
+}
@@ -0,0 +1,174 @@
+//! Measure the diversity of a population
+//!
+//! Diversity is the average pairwise Hamming distance of the piece
+//! placements, normalized to the number of pieces. 0.0 means all
+//! individuals are equal, 1.0 means no two individuals share the
+//! placement of any piece.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::gene::Gene;
+use super::{Placement, Settings};
+
+/// What to do if the diversity drops below the threshold
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiversityAction {
+    /// replace a part of the population by random individuals
+    Reseed,
+    /// raise the mutation rate until the diversity recovers
+    Boost,
+    /// restart with random individuals, keep only the elite archive
+    Restart,
+}
+
+impl FromStr for DiversityAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reseed" => Ok(DiversityAction::Reseed),
+            "boost" => Ok(DiversityAction::Boost),
+            "restart" => Ok(DiversityAction::Restart),
+            _ => Err(format!("Unknown diversity action {}", s)),
+        }
+    }
+}
+
+/// The reaction the driver loop has to apply to the running evolution
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    /// use this mutation rate from now on
+    MutationRate(f64),
+    /// replace that many individuals by random ones
+    Reseed(usize),
+    /// restart with the elite archive
+    Restart,
+}
+
+/// Decides per generation how to react on the measured diversity
+#[derive(Clone, Debug)]
+pub struct DiversityControl {
+    threshold: Option<f64>,
+    action: DiversityAction,
+    reseed: usize,
+    base_rate: f64,
+    boosted_rate: f64,
+    cooldown: u64,
+    last_reaction: Option<u64>,
+    boosted: bool,
+}
+
+impl DiversityControl {
+    pub fn new(settings: &Settings, mutation_rate: f64) -> Self {
+        DiversityControl {
+            threshold: settings.diversity_threshold,
+            action: settings.diversity_action,
+            reseed: (settings.population as f64 * settings.reseed_ratio).round() as usize,
+            base_rate: mutation_rate,
+            boosted_rate: mutation_rate * settings.mutation_boost,
+            cooldown: settings.diversity_cooldown,
+            last_reaction: None,
+            boosted: false,
+        }
+    }
+
+    /// Returns the reaction on the diversity of this generation, if any
+    ///
+    /// A boosted mutation rate is reset as soon as the diversity recovered.
+    pub fn check(&mut self, generation: u64, diversity: f64) -> Option<Response> {
+        let threshold = self.threshold?;
+        if diversity >= threshold {
+            if self.boosted {
+                self.boosted = false;
+                return Some(Response::MutationRate(self.base_rate));
+            }
+            return None;
+        }
+        if let Some(last) = self.last_reaction {
+            if generation < last + self.cooldown {
+                return None;
+            }
+        }
+        let response = match self.action {
+            DiversityAction::Reseed => Response::Reseed(self.reseed),
+            DiversityAction::Boost if self.boosted => return None,
+            DiversityAction::Boost => {
+                self.boosted = true;
+                Response::MutationRate(self.boosted_rate)
+            }
+            DiversityAction::Restart => Response::Restart,
+        };
+        self.last_reaction = Some(generation);
+        Some(response)
+    }
+}
+
+/// Average pairwise Hamming distance of the placements
+///
+/// Counting how often a placement occurs per piece avoids comparing
+/// all pairs: per piece sum(count^2) pairs are equal.
+pub fn diversity(population: &[Placement]) -> f64 {
+    let n = population.len();
+    if n < 2 || population[0].is_empty() {
+        return 0.0;
+    }
+    let pieces = population[0].len();
+    let mut counts: Vec<HashMap<Gene, usize>> = vec![HashMap::new(); pieces];
+    for placement in population {
+        for (count, gene) in counts.iter_mut().zip(placement) {
+            *count.entry(*gene).or_insert(0) += 1;
+        }
+    }
+    let different_pairs: usize = counts
+        .iter()
+        .map(|count| n * n - count.values().map(|c| c * c).sum::<usize>())
+        .sum();
+    different_pairs as f64 / (n * (n - 1) * pieces) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::PIECES;
+
+    fn placement(index: usize) -> Placement {
+        (0..PIECES).map(|name| Gene::nth(name, index)).collect()
+    }
+
+    #[test]
+    fn test_diversity_of_equal_population() {
+        let population = vec![placement(0), placement(0), placement(0)];
+        assert_eq!(diversity(&population), 0.0);
+    }
+
+    #[test]
+    fn test_diversity_of_distinct_population() {
+        let population = vec![placement(0), placement(1), placement(2)];
+        assert_eq!(diversity(&population), 1.0);
+        let population = vec![placement(0), placement(0), placement(1), placement(1)];
+        // 4 of 6 pairs differ
+        assert!((diversity(&population) - 4.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diversity_control_boost() {
+        let settings = Settings {
+            diversity_threshold: Some(0.1),
+            diversity_action: DiversityAction::Boost,
+            mutation_boost: 2.0,
+            ..Settings::default()
+        };
+        let mut control = DiversityControl::new(&settings, 0.05);
+        assert_eq!(control.check(1, 0.5), None);
+        assert_eq!(control.check(2, 0.05), Some(Response::MutationRate(0.1)));
+        assert_eq!(control.check(3, 0.05), None);
+        assert_eq!(control.check(4, 0.2), Some(Response::MutationRate(0.05)));
+    }
+
+    #[test]
+    fn test_diversity_action_from_str() {
+        assert_eq!("boost".parse(), Ok(DiversityAction::Boost));
+        assert!("none".parse::<DiversityAction>().is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! Compact gene: the dense id of a valid placement of a piece
+//!
+//! The lower 15 bits are the id in the placement lookup table, the highest
+//! bit marks a pinned piece. Name, combination and cells of the piece are
+//! looked up when needed, so cloning a genome copies 2 bytes per piece.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::piece::{Piece, DIMENSIONS, SHAPE_POINT};
+use crate::placements::{self, Entry};
+
+const PINNED: u16 = 1 << 15;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gene(u16);
+
+/// Genes are ordered by their placement id, the pin flag does not move a
+/// gene out of the bounds of the mutations
+///
+/// A pinned and an unpinned gene of the same id are not comparable.
+impl PartialOrd for Gene {
+    fn partial_cmp(&self, other: &Gene) -> Option<Ordering> {
+        match self.id().cmp(&other.id()) {
+            Ordering::Equal if self != other => None,
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl Gene {
+    /// The gene of the first placement id, the lower bound of the mutations
+    pub fn lowest() -> Gene {
+        Gene(0)
+    }
+
+    /// The gene of the last placement id, range and upper bound of the mutations
+    pub fn highest() -> Gene {
+        Gene((placements::entries().len() - 1) as u16)
+    }
+
+    /// The first valid configuration of a piece
+    pub fn first(name: usize) -> Gene {
+        Gene(placements::range(name).start as u16)
+    }
+
+    /// The gene of a placement id
+    pub fn with_id(id: usize) -> Gene {
+        Gene(id as u16)
+    }
+
+    /// A piece at a combination, if it is a valid configuration
+    pub fn placed(name: usize, combination: u16) -> Option<Gene> {
+        placements::id(name, combination).map(|id| Gene(id as u16))
+    }
+
+    /// The gene of the `index`th valid configuration of a piece
+    pub fn nth(name: usize, index: usize) -> Gene {
+        let range = placements::range(name);
+        Gene((range.start + index % range.len()) as u16)
+    }
+
+    fn entry(self) -> &'static Entry {
+        placements::entry(self.id())
+    }
+
+    pub fn id(self) -> usize {
+        (self.0 & !PINNED) as usize
+    }
+
+    pub fn get_name(self) -> usize {
+        self.entry().name
+    }
+
+    pub fn get_combination(self) -> u16 {
+        self.entry().combination
+    }
+
+    pub fn get_cells(self) -> &'static [[isize; DIMENSIONS]; SHAPE_POINT] {
+        &self.entry().cells
+    }
+
+    pub fn cell_mask(self) -> u128 {
+        self.entry().mask
+    }
+
+    /// mark the piece as fixed at its current configuration
+    pub fn pin(&mut self) {
+        self.0 |= PINNED;
+    }
+
+    pub fn is_pinned(self) -> bool {
+        self.0 & PINNED != 0
+    }
+
+    /// The next valid configuration of the same piece, after the last comes the first
+    pub fn next_valid(self) -> Gene {
+        let range = placements::range(self.get_name());
+        let id = if self.id() + 1 < range.end {
+            self.id() + 1
+        } else {
+            range.start
+        };
+        Gene(id as u16 | self.0 & PINNED)
+    }
+
+    /// The full piece for printing
+    pub fn piece(self) -> Piece {
+        let mut p = Piece::new(self.get_name());
+        p.set_combination(Some(self.get_combination()));
+        p.set_piece();
+        p
+    }
+}
+
+impl From<&Piece> for Gene {
+    fn from(p: &Piece) -> Gene {
+        Gene::placed(p.get_name(), p.get_combination())
+            .unwrap_or_else(|| panic!("piece {} is no valid configuration", p.get_name()))
+    }
+}
+
+impl fmt::Debug for Gene {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}{}",
+            crate::i2c(self.get_name() as isize),
+            self.get_combination(),
+            if self.is_pinned() { "!" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::{valid_combinations, PIECES};
+
+    #[test]
+    fn test_next_valid_wraps_around() {
+        let combinations = valid_combinations(7);
+        let mut gene = Gene::placed(7, *combinations.last().unwrap()).unwrap();
+        gene.pin();
+        assert_eq!(gene.next_valid().get_combination(), combinations[0]);
+        assert!(gene.next_valid().is_pinned());
+        assert_eq!(gene.next_valid().next_valid().id(), Gene::nth(7, 1).id());
+        assert_eq!(Gene::lowest(), Gene::first(0));
+        let last = placements::range(PIECES - 1).len() - 1;
+        assert_eq!(Gene::highest(), Gene::nth(PIECES - 1, last));
+        let mut pinned = Gene::highest();
+        pinned.pin();
+        assert_eq!(pinned.partial_cmp(&Gene::highest()), None);
+        assert!(Gene::lowest() < pinned);
+        assert_eq!(
+            Gene::from(&gene.piece()),
+            Gene::nth(7, combinations.len() - 1)
+        );
+    }
+}
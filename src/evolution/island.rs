@@ -0,0 +1,250 @@
+//! Island model: several populations evolve in parallel threads
+//!
+//! Each island has its own seed and optionally its own mutation rate.
+//! Every `migration_interval` generations the best individuals of an
+//! island migrate to the next island of the ring, an interval of 0 turns
+//! migration off. Migration is synchronous, so seeded runs are
+//! reproducible.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, Receiver, Sender},
+    Arc,
+};
+use std::thread;
+
+use crate::bounded::Usage;
+
+use super::{evolve, report_hall_of_fame, run_seed, AsPhenotype, Outcome, Placement, Settings};
+
+/// Progress messages of the islands to the combined report
+enum Message {
+    Generation {
+        island: usize,
+        generation: u64,
+        best: usize,
+        average: usize,
+        diversity: f64,
+        mutation_rate: f64,
+    },
+    Done {
+        island: usize,
+        generation: u64,
+    },
+}
+
+/// The connection of one population to the island model
+pub struct Island {
+    index: usize,
+    outbox: Sender<Vec<Placement>>,
+    inbox: Receiver<Vec<Placement>>,
+    progress: Sender<Message>,
+    solved: Arc<AtomicBool>,
+    generation: Cell<u64>,
+}
+
+impl Island {
+    pub fn report(
+        &self,
+        generation: u64,
+        best: usize,
+        average: usize,
+        diversity: f64,
+        mutation_rate: f64,
+    ) {
+        self.generation.set(generation);
+        // the report ends only after all islands are done
+        self.progress
+            .send(Message::Generation {
+                island: self.index,
+                generation,
+                best,
+                average,
+                diversity,
+                mutation_rate,
+            })
+            .unwrap();
+    }
+
+    /// Send the own migrants to the next island and receive the ones of
+    /// the previous island
+    ///
+    /// If the previous island has finished already there are no migrants.
+    pub fn migrate(&self, migrants: Vec<Placement>) -> Vec<Placement> {
+        // the next island might have finished already, which is fine
+        let _ = self.outbox.send(migrants);
+        self.inbox.recv().unwrap_or_default()
+    }
+
+    /// Signal all islands that the target fitness is reached
+    pub fn solved(&self) {
+        self.solved.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.solved.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Island {
+    fn drop(&mut self) {
+        let _ = self.progress.send(Message::Done {
+            island: self.index,
+            generation: self.generation.get(),
+        });
+    }
+}
+
+/// Is it time to migrate after the generation, never if the interval is 0
+pub fn is_migration(generation: u64, interval: u64) -> bool {
+    interval != 0 && generation.is_multiple_of(interval)
+}
+
+pub fn solve_islands(settings: &Settings, seeds: &[Placement]) {
+    let count = settings.islands;
+    let solved = Arc::new(AtomicBool::new(false));
+    let (progress, reports) = channel();
+    let (outboxes, inboxes): (Vec<_>, Vec<_>) = (0..count).map(|_| channel()).unzip();
+    let islands: Vec<Island> = inboxes
+        .into_iter()
+        .enumerate()
+        .map(|(index, inbox)| Island {
+            index,
+            // ring topology: send to the next island
+            outbox: outboxes[(index + 1) % count].clone(),
+            inbox,
+            progress: progress.clone(),
+            solved: solved.clone(),
+            generation: Cell::new(0),
+        })
+        .collect();
+    drop(outboxes);
+    drop(progress);
+
+    let outcomes: Vec<Outcome> = thread::scope(|scope| {
+        let handles: Vec<_> = islands
+            .into_iter()
+            .map(|island| {
+                let mut island_settings = settings.clone();
+                if !settings.island_mutation_rates.is_empty() {
+                    let rates = &settings.island_mutation_rates;
+                    island_settings.mutation_rate = rates[island.index % rates.len()];
+                }
+                let seed = run_seed(settings.seed, island.index);
+                scope.spawn(move || evolve(&island_settings, seed, seeds, Some(island)))
+            })
+            .collect();
+        print_progress(reports, count);
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    for (index, outcome) in outcomes.iter().enumerate() {
+        println!(
+            "Island {}: generation: {}, best solution with fitness {} found in generation {}, \
+             stop criterion: {}",
+            index, outcome.generations, outcome.fitness, outcome.generation, outcome.stop_reason
+        );
+    }
+    let (index, best) = outcomes
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, outcome)| (outcome.fitness, std::cmp::Reverse(*index)))
+        .unwrap();
+    println!(
+        "Final Best of island {} with fitness {}: {}",
+        index,
+        best.fitness,
+        best.best.as_printbox()
+    );
+    let mut cache = Usage::default();
+    for outcome in &outcomes {
+        cache.merge(&outcome.cache);
+    }
+    cache.report("Fitness cache of all islands", "placements");
+    let mut hall_of_fame = outcomes[0].hall_of_fame.clone();
+    for outcome in &outcomes[1..] {
+        hall_of_fame.merge(&outcome.hall_of_fame);
+    }
+    report_hall_of_fame(settings, &hall_of_fame);
+}
+
+/// Island, best and average fitness, diversity and mutation rate of a generation
+type Report = (usize, usize, usize, f64, f64);
+
+/// Print one combined line per generation, as soon as all islands that
+/// are still running at that generation have reported it
+fn print_progress(reports: Receiver<Message>, islands: usize) {
+    let mut finished_at: Vec<Option<u64>> = vec![None; islands];
+    let mut generations: BTreeMap<u64, Vec<Report>> = BTreeMap::new();
+    for message in reports {
+        match message {
+            Message::Generation {
+                island,
+                generation,
+                best,
+                average,
+                diversity,
+                mutation_rate,
+            } => generations.entry(generation).or_default().push((
+                island,
+                best,
+                average,
+                diversity,
+                mutation_rate,
+            )),
+            Message::Done { island, generation } => finished_at[island] = Some(generation),
+        }
+        while let Some(entry) = generations.first_entry() {
+            let generation = *entry.key();
+            let expected = finished_at
+                .iter()
+                .filter(|finished| finished.is_none_or(|last| last >= generation))
+                .count();
+            if entry.get().len() < expected {
+                break;
+            }
+            print_generation(generation, entry.remove());
+        }
+    }
+}
+
+fn print_generation(generation: u64, mut reports: Vec<Report>) {
+    reports.sort_by_key(|report| report.0);
+    let best: Vec<usize> = reports.iter().map(|report| report.1).collect();
+    let average = reports.iter().map(|report| report.2).sum::<usize>() / reports.len();
+    let diversity = reports.iter().map(|report| report.3).sum::<f64>() / reports.len() as f64;
+    let rates: Vec<String> = reports
+        .iter()
+        .map(|report| format!("{:.4}", report.4))
+        .collect();
+    println!(
+        "Step: generation: {}, best fitness per island: {:?}, global best fitness: {}, \
+         average_fitness: {}, diversity: {:.3}, mutation rate per island: [{}]",
+        generation,
+        best,
+        best.iter().max().unwrap(),
+        average,
+        diversity,
+        rates.join(", ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_interval() {
+        assert!(!is_migration(1, 25));
+        assert!(is_migration(25, 25));
+        assert!(is_migration(50, 25));
+        assert!(is_migration(3, 1));
+        assert!(!is_migration(1, 0));
+        assert!(!is_migration(25, 0));
+    }
+}
@@ -0,0 +1,458 @@
+//! NSGA-II: multi-objective evolution with a Pareto front instead of one fitness
+//!
+//! The genome places every piece anywhere in the box, also where its
+//! anchor of FIXED_MAP is not met. The objectives are
+//!
+//! * covered cells, cells with exactly one piece (maximize)
+//! * overlapping cells, cells with more than one piece (minimize)
+//! * anchor violations, pieces off their anchor (minimize)
+//!
+//! Every generation parents and offspring are sorted into fronts of
+//! non-dominated individuals, the next generation takes whole fronts and
+//! breaks the tie in the last front by crowding distance.
+//!
+//! The target fitness is reached by an individual without anchor
+//! violations that covers as many cells, the fitness it has in the
+//! placement encoding. The stagnation limit and the mutation schedule watch
+//! the most covered cells of all individuals.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use genevo::random::{random_index, Prng, Rng, Seed, SeedableRng};
+use genevo::termination::StopFlag;
+use rayon::prelude::*;
+
+use crate::cube::{AddToBox, PrintBox};
+use crate::piece::{Piece, PIECES, SHAPE_POINT};
+use crate::placements::{self, Entry};
+
+use super::schedule::RateSchedule;
+use super::termination::StagnationLimit;
+use super::Settings;
+
+/// Index of every piece into the placements of the shape in the box
+type Genome = Vec<usize>;
+
+/// The objective values of a genome
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Objectives {
+    covered: usize,
+    overlaps: usize,
+    violations: usize,
+}
+
+impl Objectives {
+    /// At least as good in all objectives and better in one
+    fn dominates(&self, other: &Objectives) -> bool {
+        self.covered >= other.covered
+            && self.overlaps <= other.overlaps
+            && self.violations <= other.violations
+            && self != other
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Individual {
+    genome: Genome,
+    objectives: Objectives,
+    rank: usize,
+    crowding: f64,
+}
+
+/// The placements of the shape and which piece may use them
+struct Problem {
+    /// all pieces have the same shape, so the free piece 'y' has all placements
+    shapes: &'static [Entry],
+    /// per piece and placement: the anchor is not met
+    violations: Vec<Vec<bool>>,
+    /// per piece the fixed placement index of a pinned piece
+    pinned: Vec<Option<usize>>,
+}
+
+impl Problem {
+    fn new(settings: &Settings) -> Self {
+        let shapes = &placements::entries()[placements::range(PIECES - 1)];
+        let violations = (0..PIECES)
+            .map(|name| {
+                shapes
+                    .iter()
+                    .map(|shape| {
+                        let mut p = Piece::new(name);
+                        p.set_combination(Some(shape.combination));
+                        p.set_piece();
+                        !p.fit_to_position()
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut pinned = vec![None; PIECES];
+        for gene in settings.pins.genes() {
+            pinned[gene.get_name()] = shapes
+                .iter()
+                .position(|shape| shape.combination == gene.get_combination());
+        }
+        Problem {
+            shapes,
+            violations,
+            pinned,
+        }
+    }
+
+    fn evaluate(&self, genome: &[usize]) -> Objectives {
+        let mut once: u128 = 0;
+        let mut twice: u128 = 0;
+        let mut violations = 0;
+        for (name, index) in genome.iter().enumerate() {
+            let mask = self.shapes[*index].mask;
+            twice |= once & mask;
+            once |= mask;
+            if self.violations[name][*index] {
+                violations += 1;
+            }
+        }
+        Objectives {
+            covered: (once & !twice).count_ones() as usize,
+            overlaps: twice.count_ones() as usize,
+            violations,
+        }
+    }
+
+    fn individual(&self, genome: Genome) -> Individual {
+        Individual {
+            objectives: self.evaluate(&genome),
+            genome,
+            rank: 0,
+            crowding: 0.0,
+        }
+    }
+
+    /// Evaluate in parallel, the genomes are bred in order before
+    fn individuals(&self, genomes: Vec<Genome>) -> Vec<Individual> {
+        genomes
+            .into_par_iter()
+            .map(|genome| self.individual(genome))
+            .collect()
+    }
+
+    fn random<R: Rng>(&self, rng: &mut R) -> Genome {
+        (0..PIECES)
+            .map(|name| self.pinned[name].unwrap_or_else(|| random_index(rng, self.shapes.len())))
+            .collect()
+    }
+
+    /// Single point crossover and a random new placement per mutated piece
+    fn offspring<R: Rng>(
+        &self,
+        first: &[usize],
+        second: &[usize],
+        rate: f64,
+        rng: &mut R,
+    ) -> Genome {
+        let cut = random_index(rng, PIECES);
+        (0..PIECES)
+            .map(|name| {
+                if let Some(index) = self.pinned[name] {
+                    index
+                } else if rng.gen::<f64>() < rate {
+                    random_index(rng, self.shapes.len())
+                } else if name < cut {
+                    first[name]
+                } else {
+                    second[name]
+                }
+            })
+            .collect()
+    }
+
+    fn printbox(&self, genome: &[usize]) -> PrintBox {
+        let mut b = PrintBox::new();
+        for (name, index) in genome.iter().enumerate() {
+            for [x, y, z] in &self.shapes[*index].cells {
+                b.add(*x as usize, *y as usize, *z as usize, name);
+            }
+        }
+        b
+    }
+}
+
+/// Sort into fronts, set rank and crowding distance, return the fronts
+fn sort_fronts(population: &mut [Individual]) -> Vec<Vec<usize>> {
+    let n = population.len();
+    let mut dominated: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut dominators = vec![0usize; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            if population[i]
+                .objectives
+                .dominates(&population[j].objectives)
+            {
+                dominated[i].push(j);
+                dominators[j] += 1;
+            } else if population[j]
+                .objectives
+                .dominates(&population[i].objectives)
+            {
+                dominated[j].push(i);
+                dominators[i] += 1;
+            }
+        }
+    }
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..n).filter(|i| dominators[*i] == 0).collect();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for i in &front {
+            population[*i].rank = fronts.len();
+            for j in &dominated[*i] {
+                dominators[*j] -= 1;
+                if dominators[*j] == 0 {
+                    next.push(*j);
+                }
+            }
+        }
+        crowding_distance(population, &front);
+        fronts.push(front);
+        front = next;
+    }
+    fronts
+}
+
+fn crowding_distance(population: &mut [Individual], front: &[usize]) {
+    for i in front {
+        population[*i].crowding = 0.0;
+    }
+    let objectives: [fn(&Objectives) -> usize; 3] =
+        [|o| o.covered, |o| o.overlaps, |o| o.violations];
+    for objective in objectives.iter() {
+        let mut sorted = front.to_vec();
+        sorted.sort_by_key(|i| objective(&population[*i].objectives));
+        let low = objective(&population[sorted[0]].objectives);
+        let high = objective(&population[sorted[sorted.len() - 1]].objectives);
+        population[sorted[0]].crowding = f64::INFINITY;
+        population[sorted[sorted.len() - 1]].crowding = f64::INFINITY;
+        if high == low {
+            continue;
+        }
+        for k in 1..sorted.len().saturating_sub(1) {
+            let gap = objective(&population[sorted[k + 1]].objectives)
+                - objective(&population[sorted[k - 1]].objectives);
+            population[sorted[k]].crowding += gap as f64 / (high - low) as f64;
+        }
+    }
+}
+
+/// Lower rank first, then larger crowding distance
+fn crowded_order(a: &Individual, b: &Individual) -> Ordering {
+    a.rank.cmp(&b.rank).then(
+        b.crowding
+            .partial_cmp(&a.crowding)
+            .unwrap_or(Ordering::Equal),
+    )
+}
+
+/// Binary tournament by crowded comparison
+fn tournament<'a, R: Rng>(population: &'a [Individual], rng: &mut R) -> &'a Individual {
+    let a = &population[random_index(rng, population.len())];
+    let b = &population[random_index(rng, population.len())];
+    if crowded_order(a, b) == Ordering::Greater {
+        b
+    } else {
+        a
+    }
+}
+
+/// Most covered cells of an individual without anchor violations
+fn fitness(population: &[Individual]) -> usize {
+    population
+        .iter()
+        .filter(|i| i.objectives.violations == 0)
+        .map(|i| i.objectives.covered)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Most covered cells of any individual
+fn most_covered(population: &[Individual]) -> usize {
+    population
+        .iter()
+        .map(|i| i.objectives.covered)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Evolve until a termination criterion is reached and print the Pareto front
+pub fn evolve(settings: &Settings, seed: Seed) {
+    let problem = Problem::new(settings);
+    let mut rng = Prng::from_seed(seed);
+    let started = Instant::now();
+    let time_limit = settings.time_limit.map(Duration::from_secs);
+    let target_fitness = settings.target_fitness.unwrap_or(PIECES * SHAPE_POINT);
+    let mut stagnation = settings.stagnation.map(StagnationLimit::new);
+    let mut schedule = RateSchedule::new(settings);
+    let genomes = (0..settings.population)
+        .map(|_| problem.random(&mut rng))
+        .collect();
+    let mut population = problem.individuals(genomes);
+    sort_fronts(&mut population);
+    let mut generation = 0;
+    let stop_reason = loop {
+        if generation >= settings.generations {
+            break format!(
+                "Simulation stopped after the limit of {} generations have been processed.",
+                settings.generations
+            );
+        }
+        if time_limit.is_some_and(|limit| started.elapsed() >= limit) {
+            break format!(
+                "Simulation stopped after the time limit of {:?}.",
+                time_limit.unwrap()
+            );
+        }
+        let best = fitness(&population);
+        if best >= target_fitness {
+            break format!(
+                "Simulation stopped after a solution with a fitness of {} has been found.",
+                best
+            );
+        }
+        let covered = most_covered(&population);
+        if let Some(StopFlag::StopNow(reason)) = stagnation
+            .as_mut()
+            .map(|limit| limit.check(generation, &covered))
+        {
+            break reason;
+        }
+        generation += 1;
+        let genomes = (0..settings.population)
+            .map(|_| {
+                let first = tournament(&population, &mut rng);
+                let second = tournament(&population, &mut rng);
+                problem.offspring(&first.genome, &second.genome, schedule.rate(), &mut rng)
+            })
+            .collect();
+        population.extend(problem.individuals(genomes));
+        let fronts = sort_fronts(&mut population);
+        let mut next: Vec<Individual> = Vec::with_capacity(settings.population);
+        for front in fronts {
+            let mut members: Vec<Individual> =
+                front.iter().map(|i| population[*i].clone()).collect();
+            if next.len() + members.len() > settings.population {
+                members.sort_by(crowded_order);
+                members.truncate(settings.population - next.len());
+            }
+            next.extend(members);
+            if next.len() == settings.population {
+                break;
+            }
+        }
+        population = next;
+        let front: Vec<&Individual> = population.iter().filter(|i| i.rank == 0).collect();
+        let rate = schedule.next(generation, most_covered(&population));
+        if !settings.quiet {
+            println!(
+                "Step: generation: {}, best fitness: {}, front size: {}, most covered cells: {}, \
+                 fewest overlaps: {}, fewest anchor violations: {}, mutation rate: {:.4}",
+                generation,
+                fitness(&population),
+                front.len(),
+                most_covered(&population),
+                front
+                    .iter()
+                    .map(|i| i.objectives.overlaps)
+                    .min()
+                    .unwrap_or(0),
+                front
+                    .iter()
+                    .map(|i| i.objectives.violations)
+                    .min()
+                    .unwrap_or(0),
+                rate
+            );
+        }
+    };
+    if !settings.quiet {
+        println!("Stop criterion: {}", stop_reason);
+        print_front(&problem, &population);
+    }
+}
+
+/// Print the distinct members of the first front, most covered cells first
+fn print_front(problem: &Problem, population: &[Individual]) {
+    let mut front: Vec<&Individual> = population.iter().filter(|i| i.rank == 0).collect();
+    front.sort_by(|a, b| {
+        b.objectives
+            .covered
+            .cmp(&a.objectives.covered)
+            .then(a.objectives.overlaps.cmp(&b.objectives.overlaps))
+    });
+    let mut known = HashSet::new();
+    front.retain(|i| known.insert(i.genome.clone()));
+    println!("Pareto front with {} placements", front.len());
+    for (rank, individual) in front.iter().enumerate() {
+        println!(
+            "Pareto #{}: covered cells: {}, overlaps: {}, anchor violations: {}: {}",
+            rank + 1,
+            individual.objectives.covered,
+            individual.objectives.overlaps,
+            individual.objectives.violations,
+            problem.printbox(&individual.genome)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn individual(covered: usize, overlaps: usize, violations: usize) -> Individual {
+        Individual {
+            genome: Vec::new(),
+            objectives: Objectives {
+                covered,
+                overlaps,
+                violations,
+            },
+            rank: 0,
+            crowding: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_fitness_without_violations() {
+        let population = vec![individual(100, 5, 3), individual(90, 2, 0)];
+        assert_eq!(fitness(&population), 90);
+        assert_eq!(fitness(&population[..1]), 0);
+        assert_eq!(most_covered(&population), 100);
+    }
+
+    #[test]
+    fn test_sort_fronts() {
+        let mut population = vec![
+            individual(100, 5, 3),
+            individual(90, 2, 3),
+            individual(90, 5, 3),
+            individual(80, 6, 4),
+        ];
+        let fronts = sort_fronts(&mut population);
+        assert_eq!(fronts, vec![vec![0, 1], vec![2], vec![3]]);
+        assert!(population[0].crowding.is_infinite());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let settings = Settings::default();
+        let problem = Problem::new(&settings);
+        // every piece at the same place: 5 overlapping cells
+        let genome = vec![0; PIECES];
+        let objectives = problem.evaluate(&genome);
+        assert_eq!(objectives.covered, 0);
+        assert_eq!(objectives.overlaps, 5);
+        assert!(objectives.violations > 0);
+        assert_eq!(
+            problem.printbox(&genome).to_string().matches('#').count(),
+            5
+        );
+    }
+}
@@ -0,0 +1,528 @@
+//! Permutation encoding: the genome is the order in which the pieces are placed
+//!
+//! A deterministic first fit decoder puts each piece at the first valid
+//! placement that does not overlap the pieces placed before. Pinned pieces
+//! are placed first. Pieces that do not fit anywhere stay out of the box,
+//! the fitness is the number of covered cells.
+
+use std::str::FromStr;
+
+use chrono::Duration;
+use genevo::{
+    genetic::{Children, Parents},
+    operator::{prelude::*, CrossoverOp, GeneticOperator, MutationOp},
+    population::*,
+    prelude::*,
+    random::{random_cut_points, random_index, Rng},
+    types::fmt::Display,
+};
+
+use crate::bounded::Usage;
+use crate::cube::PrintBox;
+use crate::piece::PIECES;
+use crate::placements;
+
+use super::gene::Gene;
+use super::termination::{OptionalLimit, StagnationLimit};
+use super::{
+    report_hall_of_fame, Archive, AsPhenotype, Outcome, Placement, Problem, Settings,
+    NUM_INDIVIDUALS_PER_PARENTS, REINSERTION_RATIO, SELECTION_RATIO,
+};
+
+/// The order in which the pieces are placed
+type Order = Vec<usize>;
+
+/// How the genome of the evolution is encoded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    /// the combination index of every piece
+    Placement,
+    /// the order of the pieces for the first fit decoder
+    Permutation,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "placement" => Ok(Encoding::Placement),
+            "permutation" => Ok(Encoding::Permutation),
+            _ => Err(format!("Unknown encoding {}", s)),
+        }
+    }
+}
+
+/// Which valid placement of a piece the decoder tries first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decoder {
+    /// the order of `Piece::next_config`
+    Configs,
+    /// placements with the lowest first cell (x, then y, then z) first
+    Cells,
+}
+
+impl FromStr for Decoder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "configs" => Ok(Decoder::Configs),
+            "cells" => Ok(Decoder::Cells),
+            _ => Err(format!("Unknown decoder {}", s)),
+        }
+    }
+}
+
+/// The order based crossover of the permutation encoding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrderCrossover {
+    /// order one crossover (OX)
+    Ox,
+    /// partially mapped crossover (PMX)
+    Pmx,
+}
+
+impl FromStr for OrderCrossover {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ox" => Ok(OrderCrossover::Ox),
+            "pmx" => Ok(OrderCrossover::Pmx),
+            _ => Err(format!("Unknown order crossover {}", s)),
+        }
+    }
+}
+
+/// The mutation of the permutation encoding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrderMutation {
+    /// exchange two pieces
+    Swap,
+    /// move a piece to another position
+    Insert,
+}
+
+impl FromStr for OrderMutation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => Ok(OrderMutation::Swap),
+            "insert" => Ok(OrderMutation::Insert),
+            _ => Err(format!("Unknown order mutation {}", s)),
+        }
+    }
+}
+
+/// The result of decoding an order
+#[derive(Clone, Debug)]
+struct Decoded {
+    /// all pieces, the ones left out at their first valid combination
+    placement: Placement,
+    /// pieces that are in the box
+    placed: Vec<bool>,
+}
+
+impl Decoded {
+    /// The box with the placed pieces only
+    fn printbox(&self) -> PrintBox {
+        let placed: Placement = self
+            .placement
+            .iter()
+            .zip(&self.placed)
+            .filter(|(_, placed)| **placed)
+            .map(|(gene, _)| *gene)
+            .collect();
+        placed.as_printbox()
+    }
+
+    fn left_out(&self) -> String {
+        let names: Vec<String> = (0..PIECES)
+            .filter(|name| !self.placed[*name])
+            .map(|name| crate::i2c(name as isize).to_string())
+            .collect();
+        names.join(",")
+    }
+}
+
+/// First fit decoder, which is the fitness function of the permutation encoding
+#[derive(Clone, Debug)]
+struct FirstFit {
+    /// valid placements and their cells per piece in the order they are tried
+    candidates: Vec<Vec<(Gene, u128)>>,
+    pins: Vec<Gene>,
+}
+
+impl FirstFit {
+    fn new(decoder: Decoder, pins: &[Gene]) -> Self {
+        let candidates = (0..PIECES)
+            .map(|name| {
+                let mut candidates: Vec<(Gene, u128)> = (0..placements::range(name).len())
+                    .map(|index| {
+                        let gene = Gene::nth(name, index);
+                        (gene, gene.cell_mask())
+                    })
+                    .collect();
+                if decoder == Decoder::Cells {
+                    // stable sort keeps the next_config order for the same lowest cell
+                    candidates.sort_by_key(|(_, mask)| mask.trailing_zeros());
+                }
+                candidates
+            })
+            .collect();
+        FirstFit {
+            candidates,
+            pins: pins.to_vec(),
+        }
+    }
+
+    /// The gene of every placed piece and the covered cells
+    fn first_fit(&self, order: &[usize]) -> (Vec<Option<Gene>>, u128) {
+        let mut genes: Vec<Option<Gene>> = vec![None; PIECES];
+        let mut occupied: u128 = 0;
+        for gene in &self.pins {
+            occupied |= gene.cell_mask();
+            genes[gene.get_name()] = Some(*gene);
+        }
+        for &name in order {
+            if genes[name].is_some() {
+                continue;
+            }
+            let fit = self.candidates[name]
+                .iter()
+                .find(|(_, mask)| mask & occupied == 0);
+            if let Some((gene, mask)) = fit {
+                occupied |= mask;
+                genes[name] = Some(*gene);
+            }
+        }
+        (genes, occupied)
+    }
+
+    /// Pieces that are left out go to their first valid configuration
+    fn decode(&self, order: &[usize]) -> Decoded {
+        let (genes, _) = self.first_fit(order);
+        Decoded {
+            placement: genes
+                .iter()
+                .enumerate()
+                .map(|(name, gene)| gene.unwrap_or_else(|| Gene::first(name)))
+                .collect(),
+            placed: genes.iter().map(Option::is_some).collect(),
+        }
+    }
+}
+
+impl FitnessFunction<Order, usize> for FirstFit {
+    fn fitness_of(&self, order: &Order) -> usize {
+        self.first_fit(order).1.count_ones() as usize
+    }
+
+    fn average(&self, values: &[usize]) -> usize {
+        Problem.average(values)
+    }
+
+    fn highest_possible_fitness(&self) -> usize {
+        Problem.highest_possible_fitness()
+    }
+
+    fn lowest_possible_fitness(&self) -> usize {
+        0
+    }
+}
+
+/// Generate random orders of the pieces
+struct RandomOrder;
+
+impl GenomeBuilder<Order> for RandomOrder {
+    fn build_genome<R>(&self, _: usize, rng: &mut R) -> Order
+    where
+        R: Rng + Sized,
+    {
+        let mut order: Order = (0..PIECES).collect();
+        for i in (1..order.len()).rev() {
+            order.swap(i, random_index(rng, i + 1));
+        }
+        order
+    }
+}
+
+/// Crossover of orders by the chosen scheme
+///
+/// Every parent breeds one child with the next parent. genevo's own order
+/// crossovers cannot be used, they wrap the partner index at the genome
+/// length instead of the number of parents.
+#[derive(Clone, Debug)]
+struct OrderCrossBreeder(OrderCrossover);
+
+impl GeneticOperator for OrderCrossBreeder {
+    fn name() -> String {
+        "Order-Cross-Breeder".to_string()
+    }
+}
+
+impl CrossoverOp<Order> for OrderCrossBreeder {
+    fn crossover<R>(&self, parents: Parents<Order>, rng: &mut R) -> Children<Order>
+    where
+        R: Rng + Sized,
+    {
+        (0..parents.len())
+            .map(|index| {
+                let partner = &parents[(index + 1) % parents.len()];
+                let (start, end) = random_cut_points(rng, PIECES);
+                match self.0 {
+                    OrderCrossover::Ox => order_one(&parents[index], partner, start, end),
+                    OrderCrossover::Pmx => partially_mapped(&parents[index], partner, start, end),
+                }
+            })
+            .collect()
+    }
+}
+
+/// OX: keep `first[start..=end]`, fill the rest in the order of `second` after `end`
+fn order_one(first: &[usize], second: &[usize], start: usize, end: usize) -> Order {
+    let length = first.len();
+    let kept = &first[start..=end];
+    let mut child = first.to_vec();
+    let mut rest = (1..=length)
+        .map(|offset| second[(end + offset) % length])
+        .filter(|gene| !kept.contains(gene));
+    for offset in 1..=length - kept.len() {
+        child[(end + offset) % length] = rest.next().unwrap();
+    }
+    child
+}
+
+/// PMX: keep `first[start..=end]`, the other positions come from `second`
+/// mapped through the kept section so that no piece appears twice
+fn partially_mapped(first: &[usize], second: &[usize], start: usize, end: usize) -> Order {
+    let mut child = second.to_vec();
+    child[start..=end].copy_from_slice(&first[start..=end]);
+    for index in (0..start).chain(end + 1..first.len()) {
+        let mut gene = second[index];
+        while let Some(position) = first[start..=end].iter().position(|kept| *kept == gene) {
+            gene = second[start + position];
+        }
+        child[index] = gene;
+    }
+    child
+}
+
+/// Mutation of orders by the chosen scheme
+#[derive(Clone, Debug)]
+struct OrderMutator {
+    mutation: OrderMutation,
+    mutation_rate: f64,
+}
+
+impl GeneticOperator for OrderMutator {
+    fn name() -> String {
+        "Order-Mutator".to_string()
+    }
+}
+
+impl MutationOp<Order> for OrderMutator {
+    fn mutate<R>(&self, genome: Order, rng: &mut R) -> Order
+    where
+        R: Rng + Sized,
+    {
+        match self.mutation {
+            OrderMutation::Swap => SwapOrderMutator::new(self.mutation_rate).mutate(genome, rng),
+            OrderMutation::Insert => {
+                InsertOrderMutator::new(self.mutation_rate).mutate(genome, rng)
+            }
+        }
+    }
+}
+
+/// Run the evolution of piece orders until a termination criterion is reached
+pub fn evolve(settings: &Settings, seed: Seed) -> Outcome {
+    let target_fitness = settings
+        .target_fitness
+        .unwrap_or_else(|| Problem.highest_possible_fitness());
+    let decoder = FirstFit::new(settings.decoder, settings.pins.genes());
+    let initial_population: Population<Order> = build_population()
+        .with_genome_builder(RandomOrder)
+        .of_size(settings.population)
+        .using_seed(seed);
+
+    let mut order_sim = simulate(
+        genetic_algorithm()
+            .with_evaluation(decoder.clone())
+            .with_selection(RouletteWheelSelector::new(
+                SELECTION_RATIO,
+                NUM_INDIVIDUALS_PER_PARENTS,
+            ))
+            .with_crossover(OrderCrossBreeder(settings.order_crossover))
+            .with_mutation(OrderMutator {
+                mutation: settings.order_mutation,
+                mutation_rate: settings.mutation_rate,
+            })
+            .with_reinsertion(ElitistReinserter::new(
+                decoder.clone(),
+                false,
+                REINSERTION_RATIO,
+            ))
+            .with_initial_population(initial_population)
+            .build(),
+    )
+    .until(or(
+        or(
+            FitnessLimit::new(target_fitness),
+            GenerationLimit::new(settings.generations),
+        ),
+        or(
+            OptionalLimit(
+                settings
+                    .time_limit
+                    .map(|seconds| TimeLimit::new(Duration::seconds(seconds as i64))),
+            ),
+            OptionalLimit(settings.stagnation.map(StagnationLimit::new)),
+        ),
+    ))
+    .build_with_seed(seed);
+
+    let mut outcome = Outcome {
+        best: Vec::new(),
+        fitness: 0,
+        generation: 0,
+        generations: 0,
+        stop_reason: String::new(),
+        hall_of_fame: if settings.modulo_symmetry {
+            Archive::modulo_symmetry(settings.hall_of_fame)
+        } else {
+            Archive::new(settings.hall_of_fame)
+        },
+        cache: Usage::default(),
+    };
+    let mut best_order: Order = Vec::new();
+    loop {
+        match order_sim.step() {
+            Ok(SimResult::Intermediate(step)) => {
+                let evaluated_population = step.result.evaluated_population;
+                let best_solution = step.result.best_solution;
+                if best_order.is_empty() || best_solution.solution.fitness > outcome.fitness {
+                    best_order = best_solution.solution.genome.clone();
+                    outcome.fitness = best_solution.solution.fitness;
+                    outcome.generation = step.iteration;
+                }
+                if !settings.quiet {
+                    println!(
+                        "Step: generation: {}, average_fitness: {}, \
+                         best fitness: {}, duration: {}, processing_time: {}",
+                        step.iteration,
+                        evaluated_population.average_fitness(),
+                        best_solution.solution.fitness,
+                        step.duration.fmt(),
+                        step.processing_time.fmt()
+                    );
+                }
+                if settings.hall_of_fame > 0 {
+                    // the left out pieces are part of the placement, so its
+                    // fitness is the one of the box and not of the order
+                    for order in evaluated_population.individuals().iter() {
+                        let placement = decoder.decode(order).placement;
+                        let fitness = Problem.fitness_of(&placement);
+                        outcome.hall_of_fame.offer(&placement, fitness);
+                    }
+                }
+            }
+            Ok(SimResult::Final(step, processing_time, duration, stop_reason)) => {
+                let best_solution = step.result.best_solution;
+                if best_order.is_empty() || best_solution.solution.fitness > outcome.fitness {
+                    best_order = best_solution.solution.genome.clone();
+                    outcome.fitness = best_solution.solution.fitness;
+                    outcome.generation = step.iteration;
+                }
+                outcome.generations = step.iteration;
+                outcome.stop_reason = stop_reason;
+                let decoded = decoder.decode(&best_order);
+                outcome.best = decoded.placement.clone();
+                if settings.quiet {
+                    break;
+                }
+                println!("Stop criterion: {}", outcome.stop_reason);
+                println!(
+                    "Final result after {}: generation: {}, \
+                     best solution with fitness {} found in generation {}, \
+                     processing_time: {}",
+                    duration.fmt(),
+                    step.iteration,
+                    outcome.fitness,
+                    outcome.generation,
+                    processing_time.fmt()
+                );
+                let order: Vec<String> = best_order
+                    .iter()
+                    .map(|name| crate::i2c(*name as isize).to_string())
+                    .collect();
+                println!("Best order: {}", order.join(","));
+                println!("Left out pieces: {}", decoded.left_out());
+                println!("Final Best: {}", decoded.printbox());
+                report_hall_of_fame(settings, &outcome.hall_of_fame);
+                break;
+            }
+            Err(error) => {
+                println!("{}", error);
+                outcome.stop_reason = error.to_string();
+                break;
+            }
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::Pins;
+    use crate::piece::valid_combinations;
+
+    #[test]
+    fn test_first_fit_has_no_overlap() {
+        let order: Order = (0..PIECES).rev().collect();
+        for decoder in [Decoder::Configs, Decoder::Cells] {
+            let first_fit = FirstFit::new(decoder, &[]);
+            let decoded = first_fit.decode(&order);
+            let placed = decoded.placed.iter().filter(|placed| **placed).count();
+            assert!(placed > 0);
+            assert_eq!(first_fit.fitness_of(&order), 5 * placed);
+            assert_eq!(decoded.printbox().to_string().matches('#').count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_first_fit_places_pins_first() {
+        let combination = *valid_combinations(7).last().unwrap();
+        let pins = Pins::parse(&format!("h:{}", combination)).unwrap();
+        let decoded =
+            FirstFit::new(Decoder::Configs, pins.genes()).decode(&(0..PIECES).collect::<Order>());
+        assert!(decoded.placed[7]);
+        assert_eq!(decoded.placement[7].get_combination(), combination);
+    }
+
+    #[test]
+    fn test_order_crossovers_give_permutations() {
+        let first: Order = (0..9).collect();
+        let second: Order = vec![8, 2, 6, 7, 1, 5, 4, 0, 3];
+        assert_eq!(
+            order_one(&first, &second, 3, 5),
+            vec![6, 7, 1, 3, 4, 5, 0, 8, 2]
+        );
+        assert_eq!(
+            partially_mapped(&first, &second, 3, 5),
+            vec![8, 2, 6, 3, 4, 5, 1, 0, 7]
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("permutation".parse(), Ok(Encoding::Permutation));
+        assert_eq!("cells".parse(), Ok(Decoder::Cells));
+        assert_eq!("pmx".parse(), Ok(OrderCrossover::Pmx));
+        assert_eq!("insert".parse(), Ok(OrderMutation::Insert));
+        assert!("shuffle".parse::<OrderMutation>().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Pieces that are pinned to a combination
+//!
+//! Pinned pieces are set by the genome builders, restored by the crossover
+//! and left alone by the mutations, so the evolution only searches over
+//! the free pieces.
+
+use std::fmt;
+
+use genevo::{
+    genetic::{Children, Parents},
+    operator::{prelude::SinglePointCrossBreeder, CrossoverOp, GeneticOperator},
+    random::Rng,
+};
+
+use crate::piece::PIECES;
+use crate::placements;
+
+use super::gene::Gene;
+use super::Placement;
+
+/// The pinned pieces, at most one combination per piece
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pins {
+    genes: Vec<Gene>,
+}
+
+impl Pins {
+    /// Parse comma separated `PIECE:COMBINATION` pairs, e.g. "a:0,h:4195"
+    pub fn parse(spec: &str) -> Result<Pins, String> {
+        let mut genes: Vec<Gene> = Vec::new();
+        for token in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let mut gene = Gene::with_id(placements::parse(token)?);
+            let name = gene.get_name();
+            if genes.iter().any(|gene| gene.get_name() == name) {
+                return Err(format!("piece {} pinned twice", crate::i2c(name as isize)));
+            }
+            gene.pin();
+            genes.push(gene);
+        }
+        Ok(Pins { genes })
+    }
+
+    /// The genes of the pinned pieces
+    pub fn genes(&self) -> &[Gene] {
+        &self.genes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genes.is_empty()
+    }
+
+    /// Put the pinned pieces into a placement
+    pub fn apply(&self, placement: &mut Placement) {
+        for gene in &self.genes {
+            placement[gene.get_name()] = *gene;
+        }
+    }
+}
+
+impl fmt::Display for Pins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pins: Vec<String> = self
+            .genes
+            .iter()
+            .map(|gene| {
+                format!(
+                    "{}:{}",
+                    crate::i2c(gene.get_name() as isize),
+                    gene.get_combination()
+                )
+            })
+            .collect();
+        write!(f, "{}", pins.join(","))
+    }
+}
+
+/// Single point crossover that keeps the pinned pieces in place
+#[derive(Clone, Debug)]
+pub struct PinnedCrossBreeder {
+    breeder: SinglePointCrossBreeder,
+    pins: Pins,
+}
+
+impl PinnedCrossBreeder {
+    pub fn new(pins: Pins) -> Self {
+        PinnedCrossBreeder {
+            breeder: SinglePointCrossBreeder::new(),
+            pins,
+        }
+    }
+}
+
+impl GeneticOperator for PinnedCrossBreeder {
+    fn name() -> String {
+        "Pinned-Single-Point-Cross-Breeder".to_string()
+    }
+}
+
+impl CrossoverOp<Placement> for PinnedCrossBreeder {
+    fn crossover<R>(&self, parents: Parents<Placement>, rng: &mut R) -> Children<Placement>
+    where
+        R: Rng + Sized,
+    {
+        let mut children = self.breeder.crossover(parents, rng);
+        for child in children.iter_mut().filter(|child| child.len() == PIECES) {
+            self.pins.apply(child);
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::genes;
+    use crate::piece::valid_combinations;
+    use genevo::{
+        operator::{prelude::BreederValueMutator, MutationOp},
+        random::get_rng,
+    };
+
+    #[test]
+    fn test_parse_pins() {
+        let pins = Pins::parse("a:0, h:4195").unwrap();
+        assert_eq!(pins.to_string(), "a:0,h:4195");
+        assert!(Pins::parse("a:0,a:0").is_err());
+        assert!(Pins::parse("b:0").is_err());
+        // out of the range of combinations
+        assert!(Pins::parse("a:65535").is_err());
+        assert!(Pins::parse("b").is_err());
+        assert!(Pins::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pinned_pieces_stay() {
+        let combination = *valid_combinations(0).last().unwrap();
+        let pins = Pins::parse(&format!("a:{}", combination)).unwrap();
+        let mut rng = get_rng([1; 32]);
+        let parents: Vec<Placement> = (0..2)
+            .map(|index| {
+                let mut placement: Placement =
+                    (0..PIECES).map(|name| Gene::nth(name, index)).collect();
+                pins.apply(&mut placement);
+                placement
+            })
+            .collect();
+        let mutator =
+            BreederValueMutator::new(1.0, Gene::highest(), 3, Gene::lowest(), Gene::highest());
+        for child in PinnedCrossBreeder::new(pins).crossover(parents, &mut rng) {
+            let mutated = mutator.mutate(child, &mut rng);
+            assert_eq!(genes(&mutated)[0], combination);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Repair of mutated genomes by constraint propagation
+//!
+//! The genes are assigned to the domains of `propagate` one after the
+//! other, the pinned ones first. A gene whose placement is no longer
+//! possible, or whose propagation runs into a conflict, is taken back.
+//! The pieces of these genes get the placement the propagation forced for
+//! them if there is one and it adds no overlap, they keep their gene
+//! otherwise. Pinned genes are never replaced. The domains are settled
+//! once and cloned for every genome.
+
+use std::sync::OnceLock;
+
+use genevo::{
+    operator::{GeneticOperator, MutationOp},
+    prelude::*,
+};
+
+use crate::placements;
+use crate::propagate::Domains;
+
+use super::gene::Gene;
+use super::Placement;
+
+/// The settled domains of the empty box, `None` if settling fails
+fn settled() -> Option<&'static Domains> {
+    static SETTLED: OnceLock<Option<Domains>> = OnceLock::new();
+    SETTLED
+        .get_or_init(|| {
+            let mut domains = Domains::new();
+            domains.settle().ok().map(|_| domains)
+        })
+        .as_ref()
+}
+
+/// Number of cells that two or more genes cover
+fn overlaps(genome: &[Gene]) -> u32 {
+    let (mut once, mut twice) = (0u128, 0u128);
+    for gene in genome {
+        twice |= once & gene.cell_mask();
+        once |= gene.cell_mask();
+    }
+    twice.count_ones()
+}
+
+/// The genome with the rejected genes replaced by forced placements
+pub fn repair(genome: Placement) -> Placement {
+    let mut domains = match settled() {
+        Some(domains) => domains.clone(),
+        None => return genome,
+    };
+    let mut order: Vec<usize> = (0..genome.len()).collect();
+    order.sort_by_key(|index| !genome[*index].is_pinned());
+    let mut rejected = Vec::new();
+    for index in order {
+        let mark = domains.mark();
+        if domains.assign(genome[index].id()).is_err() {
+            domains.undo(mark);
+            // a pinned gene stays even if other pins contradict it
+            if !genome[index].is_pinned() {
+                rejected.push(index);
+            }
+        }
+    }
+    let mut repaired = genome;
+    for index in rejected {
+        let forced = domains
+            .assigned()
+            .iter()
+            .map(|id| placements::entry(*id))
+            .find(|entry| entry.name == repaired[index].get_name());
+        if let Some(entry) = forced {
+            let original = repaired[index];
+            let before = overlaps(&repaired);
+            repaired[index] = Gene::placed(entry.name, entry.combination).unwrap();
+            // a kept gene that contradicts the propagation can be in the way
+            if overlaps(&repaired) > before {
+                repaired[index] = original;
+            }
+        }
+    }
+    repaired
+}
+
+/// A mutation whose offspring is repaired if `repair` is set
+#[derive(Clone, Debug)]
+pub struct RepairingMutator<M> {
+    inner: M,
+    repair: bool,
+}
+
+impl<M> RepairingMutator<M> {
+    pub fn new(inner: M, repair: bool) -> Self {
+        RepairingMutator { inner, repair }
+    }
+}
+
+impl<M> GeneticOperator for RepairingMutator<M>
+where
+    M: GeneticOperator,
+{
+    fn name() -> String {
+        format!("Repairing-{}", M::name())
+    }
+}
+
+impl<M> MutationOp<Placement> for RepairingMutator<M>
+where
+    M: MutationOp<Placement>,
+{
+    fn mutate<R>(&self, genome: Placement, rng: &mut R) -> Placement
+    where
+        R: Rng + Sized,
+    {
+        let genome = self.inner.mutate(genome, rng);
+        if self.repair {
+            repair(genome)
+        } else {
+            genome
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::PIECES;
+    use genevo::random::{random_index, Prng, SeedableRng};
+
+    #[test]
+    fn test_repair_keeps_consistent_genes() {
+        let genome: Placement = (0..PIECES).map(Gene::first).collect();
+        let repaired = repair(genome.clone());
+        assert_eq!(repaired.len(), PIECES);
+        // the first gene is always consistent
+        assert_eq!(repaired[0], genome[0]);
+        let kept = repaired
+            .iter()
+            .zip(&genome)
+            .filter(|(repaired, original)| repaired == original)
+            .count();
+        assert!(kept > 0);
+        // the pinned gene is assigned first and survives
+        let mut pinned = genome.clone();
+        pinned[7] = Gene::placed(7, 4195).unwrap();
+        pinned[7].pin();
+        assert_eq!(repair(pinned.clone())[7], pinned[7]);
+    }
+
+    #[test]
+    fn test_repair_adds_no_overlaps() {
+        let mut rng = Prng::seed_from_u64(50);
+        let mut changed = 0;
+        for _ in 0..20 {
+            let genome: Placement = (0..PIECES)
+                .map(|name| Gene::nth(name, random_index(&mut rng, placements::range(name).len())))
+                .collect();
+            let repaired = repair(genome.clone());
+            assert!(overlaps(&repaired) <= overlaps(&genome));
+            changed += usize::from(repaired != genome);
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn test_repair_keeps_pins() {
+        // the free pieces w and x pinned to the same cells
+        let mut genome: Placement = (0..PIECES).map(Gene::first).collect();
+        genome[22].pin();
+        genome[23].pin();
+        assert_eq!(genome[22].cell_mask(), genome[23].cell_mask());
+        let repaired = repair(genome.clone());
+        assert!(repaired[22].is_pinned() && repaired[23].is_pinned());
+        assert_eq!(&repaired[22..24], &genome[22..24]);
+    }
+}
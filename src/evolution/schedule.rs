@@ -0,0 +1,205 @@
+//! Schedules that adapt the mutation rate during a run
+//!
+//! The decays move from the initial to the final rate over the generation
+//! limit, the exponential one decays linearly if one of the rates is 0.
+//! The 1/5th success rule raises the rate while more than a fifth of the
+//! recent generations improved the best fitness and lowers it otherwise.
+//! On stagnation the rate doubles after every `STAGNATION_WINDOW`
+//! generations without improvement and drops back on improvement.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use super::Settings;
+
+/// Generations the success rule and the stagnation increase look back
+const STAGNATION_WINDOW: usize = 10;
+/// Factor of the 1/5th success rule
+const SUCCESS_FACTOR: f64 = 1.22;
+const MIN_RATE: f64 = 0.001;
+const MAX_RATE: f64 = 0.5;
+
+/// How the mutation rate changes over a run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MutationSchedule {
+    Fixed,
+    Linear,
+    Exponential,
+    OneFifth,
+    Stagnation,
+}
+
+impl FromStr for MutationSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(MutationSchedule::Fixed),
+            "linear" => Ok(MutationSchedule::Linear),
+            "exponential" => Ok(MutationSchedule::Exponential),
+            "one-fifth" => Ok(MutationSchedule::OneFifth),
+            "stagnation" => Ok(MutationSchedule::Stagnation),
+            _ => Err(format!("Unknown mutation schedule {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for MutationSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MutationSchedule::Fixed => "fixed",
+            MutationSchedule::Linear => "linear",
+            MutationSchedule::Exponential => "exponential",
+            MutationSchedule::OneFifth => "one-fifth",
+            MutationSchedule::Stagnation => "stagnation",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The effective mutation rate of a run
+#[derive(Clone, Debug)]
+pub struct RateSchedule {
+    schedule: MutationSchedule,
+    initial: f64,
+    last: f64,
+    generations: u64,
+    rate: f64,
+    best: Option<usize>,
+    /// improvements of the last generations
+    successes: VecDeque<bool>,
+    stagnant: usize,
+}
+
+impl RateSchedule {
+    pub fn new(settings: &Settings) -> Self {
+        RateSchedule {
+            schedule: settings.mutation_schedule,
+            initial: settings.mutation_rate,
+            last: settings
+                .final_mutation_rate
+                .unwrap_or(settings.mutation_rate / 10.0),
+            generations: settings.generations.max(1),
+            rate: settings.mutation_rate,
+            best: None,
+            successes: VecDeque::with_capacity(STAGNATION_WINDOW),
+            stagnant: 0,
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Update the rate after a generation with the given best fitness
+    pub fn next(&mut self, generation: u64, best: usize) -> f64 {
+        let improved = self.best.is_some_and(|known| best > known);
+        if self.best.is_none_or(|known| best > known) {
+            self.best = Some(best);
+        }
+        if self.successes.len() == STAGNATION_WINDOW {
+            self.successes.pop_front();
+        }
+        self.successes.push_back(improved);
+        self.stagnant = if improved { 0 } else { self.stagnant + 1 };
+        let progress = (generation as f64 / self.generations as f64).min(1.0);
+        self.rate = match self.schedule {
+            MutationSchedule::Fixed => self.initial,
+            MutationSchedule::Linear => self.initial + (self.last - self.initial) * progress,
+            // the ratio of the rates is not defined with a rate of 0
+            MutationSchedule::Exponential if self.initial <= 0.0 || self.last <= 0.0 => {
+                self.initial + (self.last - self.initial) * progress
+            }
+            MutationSchedule::Exponential => {
+                self.initial * (self.last / self.initial).powf(progress)
+            }
+            MutationSchedule::OneFifth => {
+                if self.successes.len() < STAGNATION_WINDOW {
+                    self.rate
+                } else {
+                    let successes = self.successes.iter().filter(|s| **s).count();
+                    if successes * 5 > STAGNATION_WINDOW {
+                        (self.rate * SUCCESS_FACTOR).min(MAX_RATE)
+                    } else if successes * 5 < STAGNATION_WINDOW {
+                        (self.rate / SUCCESS_FACTOR).max(MIN_RATE)
+                    } else {
+                        self.rate
+                    }
+                }
+            }
+            MutationSchedule::Stagnation => {
+                let doublings = (self.stagnant / STAGNATION_WINDOW) as i32;
+                (self.initial * 2f64.powi(doublings)).min(MAX_RATE)
+            }
+        };
+        self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(mutation_schedule: MutationSchedule) -> RateSchedule {
+        RateSchedule::new(&Settings {
+            generations: 100,
+            mutation_rate: 0.1,
+            final_mutation_rate: Some(0.01),
+            mutation_schedule,
+            ..Settings::default()
+        })
+    }
+
+    #[test]
+    fn test_decays() {
+        let mut linear = schedule(MutationSchedule::Linear);
+        assert!((linear.next(50, 1) - 0.055).abs() < 1e-9);
+        assert!((linear.next(100, 1) - 0.01).abs() < 1e-9);
+        let mut exponential = schedule(MutationSchedule::Exponential);
+        assert!((exponential.next(50, 1) - 0.1f64.sqrt() / 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_exponential_decay_with_zero_rate() {
+        for (initial, last) in [(0.0, 0.01), (0.1, 0.0), (0.0, 0.0)] {
+            let mut exponential = RateSchedule::new(&Settings {
+                generations: 100,
+                mutation_rate: initial,
+                final_mutation_rate: Some(last),
+                mutation_schedule: MutationSchedule::Exponential,
+                ..Settings::default()
+            });
+            for generation in [1, 50, 100] {
+                let rate = exponential.next(generation, 1);
+                assert!(rate.is_finite());
+                assert!(rate >= initial.min(last) && rate <= initial.max(last));
+            }
+            assert_eq!(exponential.rate(), last);
+        }
+    }
+
+    #[test]
+    fn test_one_fifth_rule() {
+        let mut rule = schedule(MutationSchedule::OneFifth);
+        for generation in 1..=10 {
+            rule.next(generation, 1);
+        }
+        assert!(rule.rate() < 0.1);
+        let low = rule.rate();
+        for generation in 11..=20 {
+            rule.next(generation, generation as usize);
+        }
+        assert!(rule.rate() > low);
+    }
+
+    #[test]
+    fn test_stagnation_increase() {
+        let mut stagnation = schedule(MutationSchedule::Stagnation);
+        for generation in 1..=21 {
+            stagnation.next(generation, 1);
+        }
+        assert!((stagnation.rate() - 0.4).abs() < 1e-9);
+        assert!((stagnation.next(22, 2) - 0.1).abs() < 1e-9);
+    }
+}
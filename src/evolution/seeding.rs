@@ -0,0 +1,202 @@
+//! Read placements to seed the initial population
+//!
+//! A file holds one or more placements, each either
+//!
+//! * a line of combination indices: either 25 plain numbers, one per piece,
+//!   or `PIECE:COMBINATION` pairs with the piece as letter or number
+//! * a text box as printed by `lsbox` or `ge`: 5 lines of 5 groups of 5 chars
+//!
+//! Lines starting with '#' that are not part of a box are comments. Lines
+//! ending with ':' or '...' are captions as printed before a box.
+//! Pieces that are missing are placed at their first valid configuration.
+//! Every piece must be a valid configuration.
+
+use std::fs;
+
+use crate::c2i;
+use crate::cube::{HEIGHT, LENGTH, WIDTH};
+use crate::piece::PIECES;
+use crate::placements;
+
+use super::gene::Gene;
+use super::Placement;
+
+/// Read all placements of a file
+pub fn load(file: &str) -> Result<Vec<Placement>, String> {
+    let content = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+    parse(&content).map_err(|e| format!("{}: {}", file, e))
+}
+
+pub fn parse(content: &str) -> Result<Vec<Placement>, String> {
+    let mut placements = Vec::new();
+    let mut box_lines: Vec<&str> = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_end();
+        if is_box_line(line) {
+            box_lines.push(line);
+            if box_lines.len() == LENGTH {
+                placements.push(
+                    parse_box(&box_lines)
+                        .map_err(|e| format!("box ending in line {}: {}", number + 1, e))?,
+                );
+                box_lines.clear();
+            }
+            continue;
+        }
+        if !box_lines.is_empty() {
+            return Err(format!("line {}: incomplete box", number + 1));
+        }
+        if line.is_empty() || line.starts_with('#') || line.ends_with(':') || line.ends_with("...")
+        {
+            continue;
+        }
+        placements.push(parse_indices(line).map_err(|e| format!("line {}: {}", number + 1, e))?);
+    }
+    if !box_lines.is_empty() {
+        return Err("incomplete box at the end".to_string());
+    }
+    Ok(placements)
+}
+
+fn is_box_line(line: &str) -> bool {
+    let groups: Vec<&str> = line.split(' ').collect();
+    groups.len() == WIDTH
+        && groups
+            .iter()
+            .all(|g| g.chars().count() == HEIGHT && g.chars().all(|c| c2i(c).is_some()))
+}
+
+/// Complete a partial placement with pieces at their first valid configuration
+fn complete(genes: Vec<Option<Gene>>) -> Placement {
+    genes
+        .into_iter()
+        .enumerate()
+        .map(|(name, gene)| gene.unwrap_or_else(|| Gene::first(name)))
+        .collect()
+}
+
+fn parse_indices(line: &str) -> Result<Placement, String> {
+    let mut genes: Vec<Option<Gene>> = vec![None; PIECES];
+    for (position, token) in line.split_whitespace().enumerate() {
+        let id = if token.contains(':') {
+            placements::parse(token)?
+        } else if position < PIECES {
+            placements::placed(position, token)?
+        } else {
+            return Err(format!("more than {} pieces", PIECES));
+        };
+        let name = placements::entry(id).name;
+        if genes[name].is_some() {
+            return Err(format!("piece {} placed twice", crate::i2c(name as isize)));
+        }
+        genes[name] = Some(Gene::with_id(id));
+    }
+    Ok(complete(genes))
+}
+
+/// Reconstruct the pieces of a printed box
+///
+/// Cells marked as overlapping ('#') may belong to any piece.
+fn parse_box(lines: &[&str]) -> Result<Placement, String> {
+    let mut value = [[[-1isize; HEIGHT]; WIDTH]; LENGTH];
+    for (x, line) in lines.iter().enumerate() {
+        for (y, group) in line.split(' ').enumerate() {
+            for (z, c) in group.chars().enumerate() {
+                value[x][y][z] = c2i(c).unwrap();
+            }
+        }
+    }
+    let mut genes: Vec<Option<Gene>> = vec![None; PIECES];
+    for (name, slot) in genes.iter_mut().enumerate() {
+        let mut cells = Vec::new();
+        for (x, plane) in value.iter().enumerate() {
+            for (y, row) in plane.iter().enumerate() {
+                for (z, v) in row.iter().enumerate() {
+                    if *v == name as isize {
+                        cells.push([x as isize, y as isize, z as isize]);
+                    }
+                }
+            }
+        }
+        if cells.is_empty() {
+            continue;
+        }
+        let fitting = (0..placements::range(name).len())
+            .map(|index| Gene::nth(name, index))
+            .find(|gene| {
+                let covers_all = cells.iter().all(|cell| gene.get_cells().contains(cell));
+                let only_own = gene.get_cells().iter().all(|[x, y, z]| {
+                    let v = value[*x as usize][*y as usize][*z as usize];
+                    v == name as isize || v == 25
+                });
+                covers_all && only_own
+            });
+        match fitting {
+            Some(gene) => *slot = Some(gene),
+            None => {
+                return Err(format!(
+                    "piece {} is no valid configuration",
+                    crate::i2c(name as isize)
+                ))
+            }
+        }
+    }
+    Ok(complete(genes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::{genes, AsPhenotype};
+    use crate::piece::valid_combinations;
+
+    #[test]
+    fn test_parse_indices() {
+        let placements = parse("# comment\n0 1056\nc:5185 h:604\n").unwrap();
+        assert_eq!(placements.len(), 2);
+        assert_eq!(genes(&placements[0])[0], 0);
+        assert_eq!(genes(&placements[0])[1], 1056);
+        assert_eq!(genes(&placements[1])[2], 5185);
+        assert_eq!(genes(&placements[1])[7], 604);
+        // missing pieces are at the first valid configuration
+        assert_eq!(genes(&placements[1])[0], valid_combinations(0)[0]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        // 'b' must touch its corner
+        assert!(parse("b:0").is_err());
+        assert!(parse("a:0 a:0").is_err());
+        assert!(parse("z:0").is_err());
+        assert!(parse("##### ##### ##### ##### #####\n").is_err());
+    }
+
+    #[test]
+    fn test_load_invalid_combination() {
+        let file = std::env::temp_dir().join("p3d-seed-invalid-combination.txt");
+        fs::write(&file, "# seeds\n0 1056\n0 65535\n").unwrap();
+        let file = file.to_str().unwrap();
+        assert_eq!(
+            load(file),
+            Err(format!(
+                "{}: line 3: combination 65535 of piece b is no valid placement",
+                file
+            ))
+        );
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_parse_box_round_trip() {
+        let placement = parse("0 1056 5185 5217 4 10777 4163 604 521 2561 4616 17 6664 2059 515 19 520 2 2050 524 4641 4689 0 2048 2048").unwrap().remove(0);
+        let printed = format!("Final Best: {}", placement.as_printbox());
+        let parsed = parse(&printed).unwrap().remove(0);
+        // pieces that do not overlap are reconstructed exactly
+        for name in 0..PIECES {
+            let letter = crate::i2c(name as isize);
+            if printed.chars().filter(|c| *c == letter).count() == 5 {
+                assert_eq!(genes(&parsed)[name], genes(&placement)[name]);
+            }
+        }
+    }
+}
@@ -0,0 +1,393 @@
+//! Steer a running genetic evolution from the driver loop
+//!
+//! genevo keeps the population inside of the algorithm. The operators
+//! here share a `Steering` handle with the driver loop, so that the driver
+//! can change the mutation rate or replace individuals between two
+//! generations. The mutation rate is the rate of the schedule times the
+//! boost of the diversity control; a new rate applies to the next
+//! mutations, a reseed or restart only at the next reinsertion, so one
+//! generation after the diversity that triggered it was measured.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use genevo::{
+    algorithm::EvaluatedPopulation,
+    genetic::Offspring,
+    mutation::value::BreederGenomeMutation,
+    operator::{GeneticOperator, MutationOp, ReinsertionOp},
+    prelude::*,
+};
+
+use super::diversity::Response;
+
+/// A change of the population requested by the driver loop
+#[derive(Clone, Debug, PartialEq)]
+pub enum Intervention<G> {
+    /// replace that many of the worst individuals by new random ones
+    Reseed(usize),
+    /// replace the whole population by the given elite and random individuals
+    Restart(Vec<G>),
+    /// replace the worst individuals by the given ones
+    Immigrate(Vec<G>),
+}
+
+/// Handle shared between the driver loop and the steered operators
+#[derive(Clone, Debug)]
+pub struct Steering<G> {
+    // f64 stored as bits, the mutator reads it from several threads
+    mutation_rate: Arc<AtomicU64>,
+    interventions: Arc<Mutex<Vec<Intervention<G>>>>,
+    /// the configured rate, the boost of the diversity control refers to it
+    base_rate: f64,
+    /// the rate of the schedule and the boost on top of it
+    rates: Arc<Mutex<(f64, f64)>>,
+}
+
+impl<G> Steering<G> {
+    pub fn new(mutation_rate: f64) -> Self {
+        Steering {
+            mutation_rate: Arc::new(AtomicU64::new(mutation_rate.to_bits())),
+            interventions: Arc::new(Mutex::new(Vec::new())),
+            base_rate: mutation_rate,
+            rates: Arc::new(Mutex::new((mutation_rate, 1.0))),
+        }
+    }
+
+    pub fn mutation_rate(&self) -> f64 {
+        f64::from_bits(self.mutation_rate.load(Ordering::Relaxed))
+    }
+
+    pub fn set_mutation_rate(&self, rate: f64) {
+        self.mutation_rate.store(rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Follow the rate of the schedule, keeping the boost
+    pub fn set_scheduled_rate(&self, rate: f64) {
+        let mut rates = self.rates.lock().unwrap();
+        rates.0 = rate;
+        self.set_mutation_rate(rate * rates.1);
+    }
+
+    /// Apply the reaction of the diversity control, `elite` restarts
+    pub fn react<E>(&self, response: Response, elite: E)
+    where
+        E: FnOnce() -> Vec<G>,
+    {
+        match response {
+            Response::MutationRate(rate) => {
+                let mut rates = self.rates.lock().unwrap();
+                rates.1 = if self.base_rate > 0.0 {
+                    rate / self.base_rate
+                } else {
+                    1.0
+                };
+                self.set_mutation_rate(rates.0 * rates.1);
+            }
+            Response::Reseed(count) => self.intervene(Intervention::Reseed(count)),
+            Response::Restart => self.intervene(Intervention::Restart(elite())),
+        }
+    }
+
+    /// Request a change that is applied at the next reinsertion
+    pub fn intervene(&self, intervention: Intervention<G>) {
+        self.interventions.lock().unwrap().push(intervention);
+    }
+
+    fn take_interventions(&self) -> Vec<Intervention<G>> {
+        std::mem::take(&mut *self.interventions.lock().unwrap())
+    }
+}
+
+/// Breeder value mutation with the mutation rate of the steering
+#[derive(Clone, Debug)]
+pub struct SteeredMutator<G>
+where
+    G: Genotype + BreederGenomeMutation,
+{
+    steering: Steering<G>,
+    mutation_range: <G as Genotype>::Dna,
+    mutation_precision: u8,
+    min_value: <G as Genotype>::Dna,
+    max_value: <G as Genotype>::Dna,
+}
+
+impl<G> SteeredMutator<G>
+where
+    G: Genotype + BreederGenomeMutation,
+{
+    pub fn new(
+        steering: Steering<G>,
+        mutation_range: <G as Genotype>::Dna,
+        mutation_precision: u8,
+        min_value: <G as Genotype>::Dna,
+        max_value: <G as Genotype>::Dna,
+    ) -> Self {
+        SteeredMutator {
+            steering,
+            mutation_range,
+            mutation_precision,
+            min_value,
+            max_value,
+        }
+    }
+}
+
+impl<G> GeneticOperator for SteeredMutator<G>
+where
+    G: Genotype + BreederGenomeMutation,
+{
+    fn name() -> String {
+        "Steered-Breeder-Value-Mutator".to_string()
+    }
+}
+
+impl<G> MutationOp<G> for SteeredMutator<G>
+where
+    G: Genotype + BreederGenomeMutation,
+{
+    fn mutate<R>(&self, genome: G, rng: &mut R) -> G
+    where
+        R: Rng + Sized,
+    {
+        BreederGenomeMutation::mutate_genome(
+            genome,
+            self.steering.mutation_rate(),
+            &self.mutation_range,
+            self.mutation_precision,
+            &self.min_value,
+            &self.max_value,
+            rng,
+        )
+    }
+}
+
+/// Reinsertion that applies the interventions of the steering
+///
+/// The inner reinserter must deliver the new population sorted from
+/// best to worst, as the `ElitistReinserter` does. Reseeding replaces
+/// the tail of it.
+#[derive(Clone, Debug)]
+pub struct SteeredReinserter<R, B, G> {
+    inner: R,
+    builder: B,
+    steering: Steering<G>,
+}
+
+impl<R, B, G> SteeredReinserter<R, B, G> {
+    pub fn new(inner: R, builder: B, steering: Steering<G>) -> Self {
+        SteeredReinserter {
+            inner,
+            builder,
+            steering,
+        }
+    }
+}
+
+impl<R, B, G> GeneticOperator for SteeredReinserter<R, B, G>
+where
+    R: GeneticOperator,
+    B: Clone,
+    G: Clone,
+{
+    fn name() -> String {
+        format!("Steered-{}", R::name())
+    }
+}
+
+impl<R, B, G, F> ReinsertionOp<G, F> for SteeredReinserter<R, B, G>
+where
+    R: ReinsertionOp<G, F>,
+    B: GenomeBuilder<G> + Clone,
+    G: Genotype,
+    F: Fitness,
+{
+    fn combine<Rn>(
+        &self,
+        offspring: &mut Offspring<G>,
+        population: &EvaluatedPopulation<G, F>,
+        rng: &mut Rn,
+    ) -> Vec<G>
+    where
+        Rn: Rng + Sized,
+    {
+        let mut next = self.inner.combine(offspring, population, rng);
+        for intervention in self.steering.take_interventions() {
+            match intervention {
+                Intervention::Reseed(count) => {
+                    let start = next.len() - count.min(next.len());
+                    for (index, slot) in next.iter_mut().enumerate().skip(start) {
+                        *slot = self.builder.build_genome(index, rng);
+                    }
+                }
+                Intervention::Immigrate(migrants) => {
+                    let start = next.len() - migrants.len().min(next.len());
+                    for (slot, migrant) in next.iter_mut().skip(start).zip(migrants) {
+                        *slot = migrant;
+                    }
+                }
+                Intervention::Restart(elite) => {
+                    for (index, slot) in next.iter_mut().enumerate() {
+                        *slot = match elite.get(index) {
+                            Some(genome) => genome.clone(),
+                            None => self.builder.build_genome(index, rng),
+                        };
+                    }
+                }
+            }
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::diversity::{DiversityAction, DiversityControl};
+    use crate::evolution::gene::Gene;
+    use crate::evolution::{Placement, Settings};
+    use crate::piece::PIECES;
+    use genevo::random::{Prng, SeedableRng};
+    use std::rc::Rc;
+
+    /// Keeps the population as it is
+    #[derive(Clone, Debug)]
+    struct Keep;
+
+    impl GeneticOperator for Keep {
+        fn name() -> String {
+            "Keep".to_string()
+        }
+    }
+
+    impl ReinsertionOp<Placement, usize> for Keep {
+        fn combine<R>(
+            &self,
+            _: &mut Offspring<Placement>,
+            population: &EvaluatedPopulation<Placement, usize>,
+            _: &mut R,
+        ) -> Vec<Placement>
+        where
+            R: Rng + Sized,
+        {
+            population.individuals().to_vec()
+        }
+    }
+
+    /// Builds placements of the second placement of every piece
+    #[derive(Clone, Debug)]
+    struct Second;
+
+    impl GenomeBuilder<Placement> for Second {
+        fn build_genome<R>(&self, _: usize, _: &mut R) -> Placement
+        where
+            R: Rng + Sized,
+        {
+            placement(1)
+        }
+    }
+
+    fn placement(index: usize) -> Placement {
+        (0..PIECES).map(|name| Gene::nth(name, index)).collect()
+    }
+
+    fn population(size: usize) -> EvaluatedPopulation<Placement, usize> {
+        EvaluatedPopulation::new(Rc::new(vec![placement(0); size]), vec![0; size], 0, 0, 0)
+    }
+
+    #[test]
+    fn test_mutation_rate() {
+        let steering: Steering<Placement> = Steering::new(0.1);
+        let mutator = SteeredMutator::new(
+            steering.clone(),
+            Gene::highest(),
+            1,
+            Gene::lowest(),
+            Gene::highest(),
+        );
+        let mut rng = Prng::seed_from_u64(27);
+        steering.set_mutation_rate(0.0);
+        assert_eq!(mutator.mutate(placement(0), &mut rng), placement(0));
+        // the mutator reads the rate the driver set on its clone
+        steering.set_mutation_rate(1.0);
+        assert_ne!(mutator.mutate(placement(0), &mut rng), placement(0));
+    }
+
+    #[test]
+    fn test_boost_follows_the_schedule() {
+        let steering: Steering<Placement> = Steering::new(0.1);
+        steering.set_scheduled_rate(0.05);
+        assert!((steering.mutation_rate() - 0.05).abs() < 1e-12);
+        steering.react(Response::MutationRate(0.4), Vec::new);
+        assert!((steering.mutation_rate() - 0.2).abs() < 1e-12);
+        steering.set_scheduled_rate(0.02);
+        assert!((steering.mutation_rate() - 0.08).abs() < 1e-12);
+        steering.react(Response::MutationRate(0.1), Vec::new);
+        assert!((steering.mutation_rate() - 0.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_boost_around_the_threshold() {
+        let settings = Settings {
+            diversity_threshold: Some(0.1),
+            diversity_action: DiversityAction::Boost,
+            mutation_boost: 4.0,
+            ..Settings::default()
+        };
+        let mut control = DiversityControl::new(&settings, 0.1);
+        let steering: Steering<Placement> = Steering::new(0.1);
+        let mut rates = Vec::new();
+        for (generation, diversity) in [(1, 0.5), (2, 0.1), (3, 0.09), (4, 0.05), (5, 0.1)] {
+            if let Some(response) = control.check(generation, diversity) {
+                steering.react(response, Vec::new);
+            }
+            rates.push(steering.mutation_rate());
+        }
+        // the threshold itself is no loss of diversity
+        assert_eq!(rates[..2], [0.1, 0.1]);
+        assert!((rates[2] - 0.4).abs() < 1e-12);
+        assert!((rates[3] - 0.4).abs() < 1e-12);
+        // reset as soon as the diversity recovered
+        assert_eq!(rates[4], 0.1);
+    }
+
+    #[test]
+    fn test_intervention_at_next_reinsertion() {
+        let settings = Settings {
+            diversity_threshold: Some(0.1),
+            diversity_action: DiversityAction::Reseed,
+            population: 4,
+            reseed_ratio: 0.5,
+            diversity_cooldown: 2,
+            ..Settings::default()
+        };
+        let mut control = DiversityControl::new(&settings, 0.1);
+        let steering: Steering<Placement> = Steering::new(0.1);
+        let reinserter = SteeredReinserter::new(Keep, Second, steering.clone());
+        let mut rng = Prng::seed_from_u64(27);
+        let mut reinsert = || reinserter.combine(&mut Vec::new(), &population(4), &mut rng);
+        assert_eq!(control.check(1, 0.5), None);
+        steering.react(control.check(2, 0.0).unwrap(), Vec::new);
+        // requested in generation 2, the population changes on reinsertion only
+        assert_eq!(steering.interventions.lock().unwrap().len(), 1);
+        let next = reinsert();
+        assert_eq!(next[..2], [placement(0), placement(0)]);
+        assert_eq!(next[2..], [placement(1), placement(1)]);
+        // applied once, and the cooldown holds back the next reseed
+        assert_eq!(control.check(3, 0.0), None);
+        assert_eq!(reinsert(), vec![placement(0); 4]);
+        assert_eq!(control.check(4, 0.0), Some(Response::Reseed(2)));
+    }
+
+    #[test]
+    fn test_restart_with_elite() {
+        let steering: Steering<Placement> = Steering::new(0.1);
+        let reinserter = SteeredReinserter::new(Keep, Second, steering.clone());
+        steering.react(Response::Restart, || vec![placement(2)]);
+        let mut rng = Prng::seed_from_u64(27);
+        let next = reinserter.combine(&mut Vec::new(), &population(3), &mut rng);
+        assert_eq!(next, vec![placement(2), placement(1), placement(1)]);
+    }
+}
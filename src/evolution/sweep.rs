@@ -0,0 +1,360 @@
+//! Parameter sweep: run the GA for every combination of parameter values
+//!
+//! Every configuration runs with several seeds, the runs are spread over
+//! worker threads. The result is a table with one line per configuration.
+
+use std::fs;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use genevo::prelude::FitnessFunction;
+
+use super::{evolve, run_seed, use_threads, MutationSchedule, Problem, Settings};
+
+/// Output format of the result table
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableFormat {
+    Csv,
+    Markdown,
+}
+
+impl FromStr for TableFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(TableFormat::Csv),
+            "markdown" => Ok(TableFormat::Markdown),
+            _ => Err(format!("Unknown table format {}", s)),
+        }
+    }
+}
+
+/// The parameter values to combine and how to run them
+#[derive(Clone, Debug)]
+pub struct Sweep {
+    /// settings of all runs apart from the swept parameters
+    pub settings: Settings,
+    pub generations: Vec<u64>,
+    pub populations: Vec<usize>,
+    pub mutation_rates: Vec<f64>,
+    pub mutation_schedules: Vec<MutationSchedule>,
+    /// runs per configuration, with the seeds `seed`, `seed + 1`, ...
+    pub seeds: usize,
+    /// number of runs in parallel
+    pub jobs: usize,
+    pub format: TableFormat,
+    /// write the table to this file instead of printing it
+    pub output: Option<String>,
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        let settings = Settings::default();
+        Sweep {
+            generations: vec![settings.generations],
+            populations: vec![settings.population],
+            mutation_rates: vec![settings.mutation_rate],
+            mutation_schedules: vec![settings.mutation_schedule],
+            settings,
+            seeds: 5,
+            jobs: thread::available_parallelism().map_or(1, |n| n.get()),
+            format: TableFormat::Csv,
+            output: None,
+        }
+    }
+}
+
+/// Parse comma separated values and ranges `START..END:STEP` with END included
+pub fn parse_values(spec: &str) -> Result<Vec<f64>, String> {
+    let mut values = Vec::new();
+    for token in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let number = |s: &str| {
+            s.parse::<f64>()
+                .map_err(|_| format!("{} is no number in {}", s, token))
+        };
+        match token.split_once("..") {
+            None => values.push(number(token)?),
+            Some((start, rest)) => {
+                let (end, step) = rest.split_once(':').unwrap_or((rest, "1"));
+                let (start, end, step) = (number(start)?, number(end)?, number(step)?);
+                if step <= 0.0 || end < start {
+                    return Err(format!("empty range {}", token));
+                }
+                let count = ((end - start) / step + 1e-9).floor() as usize;
+                values.extend((0..=count).map(|i| {
+                    // round away the error of the floating point steps
+                    ((start + i as f64 * step) * 1e9).round() / 1e9
+                }));
+            }
+        }
+    }
+    if values.is_empty() {
+        return Err("no values".to_string());
+    }
+    Ok(values)
+}
+
+/// Like `parse_values` for whole numbers
+pub fn parse_counts(spec: &str) -> Result<Vec<u64>, String> {
+    parse_values(spec)?
+        .into_iter()
+        .map(|v| {
+            if v >= 0.0 && v.fract() == 0.0 {
+                Ok(v as u64)
+            } else {
+                Err(format!("{} is no count", v))
+            }
+        })
+        .collect()
+}
+
+/// One combination of the swept parameters
+#[derive(Clone, Debug)]
+struct Configuration {
+    generations: u64,
+    population: usize,
+    mutation_rate: f64,
+    mutation_schedule: MutationSchedule,
+}
+
+/// The result of one run
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    fitness: usize,
+    generation: u64,
+    seconds: f64,
+}
+
+fn configurations(sweep: &Sweep) -> Vec<Configuration> {
+    let mut configurations = Vec::new();
+    for generations in &sweep.generations {
+        for population in &sweep.populations {
+            for mutation_rate in &sweep.mutation_rates {
+                for mutation_schedule in &sweep.mutation_schedules {
+                    configurations.push(Configuration {
+                        generations: *generations,
+                        population: *population,
+                        mutation_rate: *mutation_rate,
+                        mutation_schedule: *mutation_schedule,
+                    });
+                }
+            }
+        }
+    }
+    configurations
+}
+
+/// Run all configurations with all seeds and report the table
+pub fn sweep(sweep: &Sweep) {
+    use_threads(sweep.settings.threads);
+    let configurations = configurations(sweep);
+    let tasks = configurations.len() * sweep.seeds;
+    let base_seed = sweep.settings.seed.unwrap_or(0);
+    let target_fitness = sweep
+        .settings
+        .target_fitness
+        .unwrap_or_else(|| Problem.highest_possible_fitness());
+    println!(
+        "Sweep over {} configurations with {} seeds each on {} threads",
+        configurations.len(),
+        sweep.seeds,
+        sweep.jobs
+    );
+    let next = AtomicUsize::new(0);
+    let runs: Mutex<Vec<Option<Run>>> = Mutex::new(vec![None; tasks]);
+    thread::scope(|scope| {
+        for _ in 0..sweep.jobs.clamp(1, tasks.max(1)) {
+            scope.spawn(|| loop {
+                let task = next.fetch_add(1, Ordering::SeqCst);
+                if task >= tasks {
+                    break;
+                }
+                let (index, seed) = (task / sweep.seeds, task % sweep.seeds);
+                let configuration = &configurations[index];
+                let settings = Settings {
+                    generations: configuration.generations,
+                    population: configuration.population,
+                    mutation_rate: configuration.mutation_rate,
+                    mutation_schedule: configuration.mutation_schedule,
+                    seed: Some(base_seed + seed as u64),
+                    quiet: true,
+                    ..sweep.settings.clone()
+                };
+                let started = Instant::now();
+                let outcome = evolve(&settings, run_seed(settings.seed, 0), &[], None);
+                let run = Run {
+                    fitness: outcome.fitness,
+                    generation: outcome.generation,
+                    seconds: started.elapsed().as_secs_f64(),
+                };
+                println!(
+                    "Configuration {} seed {}: best fitness {} in generation {} after {:.2}s",
+                    index + 1,
+                    base_seed + seed as u64,
+                    run.fitness,
+                    run.generation,
+                    run.seconds
+                );
+                runs.lock().unwrap()[task] = Some(run);
+            });
+        }
+    });
+    let runs: Vec<Run> = runs.into_inner().unwrap().into_iter().flatten().collect();
+    let rows: Vec<Row> = configurations
+        .iter()
+        .zip(runs.chunks(sweep.seeds.max(1)))
+        .map(|(configuration, runs)| Row::new(configuration, runs, target_fitness))
+        .collect();
+    let table = table(&rows, sweep.format);
+    match &sweep.output {
+        None => print!("{}", table),
+        Some(file) => match fs::write(file, table) {
+            Ok(()) => println!(
+                "Sweep table with {} configurations saved to {}",
+                rows.len(),
+                file
+            ),
+            Err(error) => println!("Cannot save sweep table to {}: {}", file, error),
+        },
+    }
+}
+
+/// The summary of all runs of a configuration
+struct Row {
+    configuration: Configuration,
+    runs: usize,
+    success_rate: f64,
+    median_fitness: f64,
+    median_generation: f64,
+    mean_seconds: f64,
+}
+
+impl Row {
+    fn new(configuration: &Configuration, runs: &[Run], target_fitness: usize) -> Self {
+        let successes = runs.iter().filter(|r| r.fitness >= target_fitness).count();
+        Row {
+            configuration: configuration.clone(),
+            runs: runs.len(),
+            success_rate: successes as f64 / runs.len().max(1) as f64,
+            median_fitness: median(runs.iter().map(|r| r.fitness as f64).collect()),
+            median_generation: median(runs.iter().map(|r| r.generation as f64).collect()),
+            mean_seconds: runs.iter().map(|r| r.seconds).sum::<f64>() / runs.len().max(1) as f64,
+        }
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.configuration.generations.to_string(),
+            self.configuration.population.to_string(),
+            self.configuration.mutation_rate.to_string(),
+            self.configuration.mutation_schedule.to_string(),
+            self.runs.to_string(),
+            format!("{:.2}", self.success_rate),
+            self.median_fitness.to_string(),
+            self.median_generation.to_string(),
+            format!("{:.3}", self.mean_seconds),
+        ]
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+const HEADER: [&str; 9] = [
+    "generations",
+    "population",
+    "mutation_rate",
+    "mutation_schedule",
+    "runs",
+    "success_rate",
+    "median_best_fitness",
+    "median_generation_of_best",
+    "mean_runtime_s",
+];
+
+fn table(rows: &[Row], format: TableFormat) -> String {
+    let mut table = String::new();
+    let mut line = |cells: &[String]| match format {
+        TableFormat::Csv => table.push_str(&format!("{}\n", cells.join(","))),
+        TableFormat::Markdown => table.push_str(&format!("| {} |\n", cells.join(" | "))),
+    };
+    line(&HEADER.iter().map(|h| h.to_string()).collect::<Vec<_>>());
+    if format == TableFormat::Markdown {
+        line(&HEADER.iter().map(|_| "---".to_string()).collect::<Vec<_>>());
+    }
+    for row in rows {
+        line(&row.cells());
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_values("100,200").unwrap(), vec![100.0, 200.0]);
+        assert_eq!(
+            parse_values("0.01..0.05:0.01").unwrap(),
+            vec![0.01, 0.02, 0.03, 0.04, 0.05]
+        );
+        assert_eq!(
+            parse_counts("100..300:100, 500").unwrap(),
+            vec![100, 200, 300, 500]
+        );
+        assert!(parse_counts("0.5").is_err());
+        assert!(parse_values("5..1").is_err());
+        assert!(parse_values("").is_err());
+    }
+
+    #[test]
+    fn test_table() {
+        let configuration = Configuration {
+            generations: 10,
+            population: 20,
+            mutation_rate: 0.05,
+            mutation_schedule: MutationSchedule::Fixed,
+        };
+        let runs = [
+            Run {
+                fitness: 90,
+                generation: 5,
+                seconds: 1.0,
+            },
+            Run {
+                fitness: 100,
+                generation: 7,
+                seconds: 2.0,
+            },
+            Run {
+                fitness: 80,
+                generation: 9,
+                seconds: 3.0,
+            },
+        ];
+        let rows = vec![Row::new(&configuration, &runs, 100)];
+        let csv = table(&rows, TableFormat::Csv);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("10,20,0.05,fixed,3,0.33,90,7,2.000")
+        );
+        let markdown = table(&rows, TableFormat::Markdown);
+        assert_eq!(markdown.lines().count(), 3);
+        assert!(markdown.starts_with("| generations | population |"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Additional termination criteria for the genetic evolution
+//!
+//! genevo already provides the fitness, generation and time limits.
+//! Here are the criteria that are missing there, so that all of them
+//! can be combined by the `or` combinator.
+
+use genevo::{
+    operator::{CrossoverOp, MutationOp, ReinsertionOp, SelectionOp},
+    prelude::*,
+    simulation::State,
+    termination::{StopFlag, Termination},
+};
+
+/// Stops the simulation if the best fitness did not improve
+/// for a number of generations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StagnationLimit<F>
+where
+    F: Fitness,
+{
+    max_stagnant: u64,
+    best: Option<F>,
+    improved_at: u64,
+}
+
+impl<F> StagnationLimit<F>
+where
+    F: Fitness,
+{
+    pub fn new(max_stagnant: u64) -> Self {
+        StagnationLimit {
+            max_stagnant,
+            best: None,
+            improved_at: 0,
+        }
+    }
+
+    /// Note the best fitness of a generation and decide whether to stop
+    pub fn check(&mut self, iteration: u64, fitness: &F) -> StopFlag {
+        let improved = match &self.best {
+            None => true,
+            Some(best) => fitness > best,
+        };
+        if improved {
+            self.best = Some(fitness.clone());
+            self.improved_at = iteration;
+            return StopFlag::Continue;
+        }
+        if iteration - self.improved_at >= self.max_stagnant {
+            StopFlag::StopNow(format!(
+                "Simulation stopped by stagnation: best fitness {:?} \
+                 did not improve for {} generations.",
+                fitness, self.max_stagnant
+            ))
+        } else {
+            StopFlag::Continue
+        }
+    }
+}
+
+impl<G, F, E, S, C, M, R> Termination<GeneticAlgorithm<G, F, E, S, C, M, R>> for StagnationLimit<F>
+where
+    G: Genotype,
+    F: Fitness + Send + Sync,
+    E: FitnessFunction<G, F> + Sync,
+    S: SelectionOp<G, F>,
+    C: CrossoverOp<G> + Sync,
+    M: MutationOp<G> + Sync,
+    R: ReinsertionOp<G, F>,
+{
+    fn evaluate(&mut self, state: &State<GeneticAlgorithm<G, F, E, S, C, M, R>>) -> StopFlag {
+        self.check(
+            state.iteration,
+            &state.result.best_solution.solution.fitness,
+        )
+    }
+
+    fn reset(&mut self) {
+        self.best = None;
+        self.improved_at = 0;
+    }
+}
+
+/// A termination criterion that can be switched off
+///
+/// If there is no criterion the simulation always continues.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptionalLimit<T>(pub Option<T>);
+
+impl<A, T> Termination<A> for OptionalLimit<T>
+where
+    A: Algorithm,
+    T: Termination<A>,
+{
+    fn evaluate(&mut self, state: &State<A>) -> StopFlag {
+        match &mut self.0 {
+            Some(limit) => limit.evaluate(state),
+            None => StopFlag::Continue,
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Some(limit) = &mut self.0 {
+            limit.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genevo::{random::Prng, statistic::ProcessingTime};
+
+    /// An algorithm without output, only the iteration of its state counts
+    #[derive(Debug)]
+    struct Idle;
+
+    impl Algorithm for Idle {
+        type Output = ();
+        type Error = std::fmt::Error;
+
+        fn next(&mut self, _: u64, _: &mut Prng) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn reset(&mut self) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+    }
+
+    fn state(iteration: u64) -> State<Idle> {
+        State {
+            started_at: chrono::Local::now(),
+            iteration,
+            duration: chrono::Duration::zero(),
+            processing_time: ProcessingTime::zero(),
+            result: (),
+        }
+    }
+
+    fn stops(flag: StopFlag) -> bool {
+        matches!(flag, StopFlag::StopNow(_))
+    }
+
+    #[test]
+    fn test_stagnation_limit() {
+        let mut limit = StagnationLimit::new(3);
+        assert!(!stops(limit.check(1, &10usize)));
+        assert!(!stops(limit.check(2, &10)));
+        assert!(!stops(limit.check(3, &10)));
+        // an improvement restarts the count
+        assert!(!stops(limit.check(4, &11)));
+        assert!(!stops(limit.check(6, &11)));
+        assert!(stops(limit.check(7, &9)));
+    }
+
+    #[test]
+    fn test_optional_limit() {
+        let mut off: OptionalLimit<GenerationLimit> = OptionalLimit(None);
+        assert!(!stops(off.evaluate(&state(1000))));
+        let mut on = OptionalLimit(Some(GenerationLimit::new(5)));
+        assert!(!stops(on.evaluate(&state(4))));
+        assert!(stops(on.evaluate(&state(5))));
+    }
+}
@@ -0,0 +1,384 @@
+//! The packing problem as 0/1 integer linear program
+//!
+//! Every valid placement is a binary variable, 1 if the piece is placed
+//! there. For feasibility every cell is covered by exactly one placement and
+//! every piece has exactly one placement. For partial packings both sums are
+//! at most one and the objective maximizes the covered cells instead.
+//!
+//! The program is written in the CPLEX LP format for external solvers.
+//! The variable of a placement is named `x_name_combination`, e.g.
+//! `x_h_4195`, since names starting with `e` read as exponent in LP files.
+//! `solve` runs a depth first branch and bound on the LP relaxations of
+//! microlp: the variable with the highest fractional value is fixed to 1
+//! first, then to 0, and a relaxation that cannot beat the best packing
+//! found cuts the branch off.
+
+use std::fmt::Write;
+use std::fs;
+use std::str::FromStr;
+use std::time::Instant;
+
+use microlp::{ComparisonOp, OptimizationDirection, Problem, Solution, Variable};
+
+use crate::cube::{position, CELLS};
+use crate::limits::Limits;
+use crate::piece::{PIECES, SHAPE_POINT};
+use crate::{i2c, placements};
+
+/// Values closer than this to 0 or 1 count as integral
+const EPSILON: f64 = 1e-6;
+/// Maximum length of a line of an LP file
+const LINE_LENGTH: usize = 255;
+
+/// What the program optimizes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Objective {
+    /// a complete packing, every cell and piece exactly once
+    Feasibility,
+    /// a partial packing with the most covered cells
+    MaxCells,
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "feasibility" => Ok(Objective::Feasibility),
+            "max-cells" => Ok(Objective::MaxCells),
+            _ => Err(format!("Unknown objective {}", s)),
+        }
+    }
+}
+
+/// A constraint that the sum of placement variables is 1 or at most 1
+#[derive(Clone, Debug, PartialEq)]
+struct Row {
+    name: String,
+    /// indexes into the placements of the program
+    columns: Vec<usize>,
+}
+
+/// The 0/1 program over a set of placement ids
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    objective: Objective,
+    ids: Vec<usize>,
+    rows: Vec<Row>,
+}
+
+/// The variable name of a placement id, e.g. `x_h_4195`
+fn variable(id: usize) -> String {
+    let entry = placements::entry(id);
+    format!("x_{}_{}", i2c(entry.name as isize), entry.combination)
+}
+
+impl Program {
+    /// The program over the placement ids, cells no placement covers are left out
+    fn new(ids: Vec<usize>, objective: Objective) -> Self {
+        let columns = |filter: &dyn Fn(usize) -> bool| -> Vec<usize> {
+            (0..ids.len()).filter(|c| filter(ids[*c])).collect()
+        };
+        let cells = (0..CELLS).map(|cell| {
+            let [x, y, z] = position(cell);
+            Row {
+                name: format!("cell_{}_{}_{}", x, y, z),
+                columns: columns(&|id| placements::entry(id).mask & 1 << cell != 0),
+            }
+        });
+        let pieces = (0..PIECES).map(|name| Row {
+            name: format!("piece_{}", i2c(name as isize)),
+            columns: columns(&|id| placements::entry(id).name == name),
+        });
+        let rows = cells
+            .chain(pieces)
+            .filter(|row| !row.columns.is_empty())
+            .collect();
+        Program {
+            objective,
+            ids,
+            rows,
+        }
+    }
+
+    /// The program over all valid placements
+    pub fn packing(objective: Objective) -> Self {
+        Program::new((0..placements::entries().len()).collect(), objective)
+    }
+
+    /// Objective coefficient of a placement variable
+    fn cost(&self) -> f64 {
+        match self.objective {
+            Objective::Feasibility => 0.0,
+            Objective::MaxCells => SHAPE_POINT as f64,
+        }
+    }
+
+    /// The program in the CPLEX LP format
+    pub fn lp(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "\\ p3d packing of the 5x5x5 box").unwrap();
+        let (sense, operator) = match self.objective {
+            Objective::Feasibility => ("Minimize", "="),
+            Objective::MaxCells => ("Maximize", "<="),
+        };
+        writeln!(text, "{}", sense).unwrap();
+        let objective: Vec<String> = self
+            .ids
+            .iter()
+            .map(|id| format!("{} {}", self.cost(), variable(*id)))
+            .collect();
+        write_terms(&mut text, " obj:", " + ", &objective, "");
+        writeln!(text, "Subject To").unwrap();
+        for row in &self.rows {
+            let terms: Vec<String> = row.columns.iter().map(|c| variable(self.ids[*c])).collect();
+            let end = format!(" {} 1", operator);
+            write_terms(&mut text, &format!(" {}:", row.name), " + ", &terms, &end);
+        }
+        writeln!(text, "Binary").unwrap();
+        let names: Vec<String> = self.ids.iter().map(|id| variable(*id)).collect();
+        write_terms(&mut text, "", " ", &names, "");
+        writeln!(text, "End").unwrap();
+        text
+    }
+}
+
+/// Terms joined by the separator after the label and the end, continued on indented lines
+fn write_terms(text: &mut String, label: &str, separator: &str, terms: &[String], end: &str) {
+    let mut line = label.to_string();
+    for (index, term) in terms.iter().enumerate() {
+        let separator = if index == 0 { " " } else { separator };
+        if line.len() + separator.len() + term.len() > LINE_LENGTH {
+            writeln!(text, "{}", line).unwrap();
+            line = String::new();
+        }
+        line.push_str(separator);
+        line.push_str(term);
+    }
+    if line.len() + end.len() > LINE_LENGTH {
+        writeln!(text, "{}", line).unwrap();
+        line = String::new();
+    }
+    writeln!(text, "{}{}", line, end).unwrap();
+}
+
+/// Depth first branch and bound over the LP relaxations
+struct BranchAndBound<'a> {
+    program: &'a Program,
+    limits: &'a Limits,
+    variables: Vec<Variable>,
+    started: Instant,
+    nodes: u64,
+    /// covered cells and placement ids of the best packing found
+    best: Option<(usize, Vec<usize>)>,
+    stop_reason: Option<String>,
+}
+
+impl<'a> BranchAndBound<'a> {
+    fn new(program: &'a Program, limits: &'a Limits) -> (Self, Problem) {
+        let direction = match program.objective {
+            Objective::Feasibility => OptimizationDirection::Minimize,
+            Objective::MaxCells => OptimizationDirection::Maximize,
+        };
+        let mut problem = Problem::new(direction);
+        let variables: Vec<Variable> = program
+            .ids
+            .iter()
+            .map(|_| problem.add_var(program.cost(), (0.0, 1.0)))
+            .collect();
+        let operator = match program.objective {
+            Objective::Feasibility => ComparisonOp::Eq,
+            Objective::MaxCells => ComparisonOp::Le,
+        };
+        for row in &program.rows {
+            let terms: Vec<(Variable, f64)> =
+                row.columns.iter().map(|c| (variables[*c], 1.0)).collect();
+            problem.add_constraint(&terms[..], operator, 1.0);
+        }
+        let search = BranchAndBound {
+            program,
+            limits,
+            variables,
+            started: Instant::now(),
+            nodes: 0,
+            best: None,
+            stop_reason: None,
+        };
+        (search, problem)
+    }
+
+    fn limit_reached(&mut self) -> bool {
+        if self.stop_reason.is_none() {
+            self.stop_reason = self.limits.exceeded(self.nodes, self.started);
+        }
+        self.stop_reason.is_some()
+    }
+
+    /// The relaxation cannot give a better packing than the best one
+    fn bounded(&self, solution: &Solution) -> bool {
+        match (self.program.objective, &self.best) {
+            (_, None) => false,
+            (Objective::Feasibility, Some(_)) => true,
+            (Objective::MaxCells, Some((cells, _))) => {
+                let pieces = ((solution.objective() + EPSILON) / SHAPE_POINT as f64).floor();
+                pieces as usize * SHAPE_POINT <= *cells
+            }
+        }
+    }
+
+    /// Search below a relaxation, fixing to 1 recursively and to 0 in the loop
+    fn branch(&mut self, mut solution: Solution) {
+        loop {
+            self.nodes += 1;
+            if self.limit_reached() || self.bounded(&solution) {
+                return;
+            }
+            let fractional = (0..self.variables.len())
+                .map(|c| (c, *solution.var_value(self.variables[c])))
+                .filter(|(_, value)| *value > EPSILON && *value < 1.0 - EPSILON)
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let column = match fractional {
+                None => {
+                    self.integral(&solution);
+                    return;
+                }
+                Some((column, _)) => column,
+            };
+            let variable = self.variables[column];
+            if let Ok(one) = solution.clone().fix_var(variable, 1.0) {
+                self.branch(one);
+                if self.stop_reason.is_some() {
+                    return;
+                }
+            }
+            solution = match solution.fix_var(variable, 0.0) {
+                Ok(zero) => zero,
+                Err(_) => return,
+            };
+        }
+    }
+
+    /// Record an integral relaxation if it is better than the best packing
+    fn integral(&mut self, solution: &Solution) {
+        let ids: Vec<usize> = (0..self.variables.len())
+            .filter(|c| *solution.var_value(self.variables[*c]) > 0.5)
+            .map(|c| self.program.ids[c])
+            .collect();
+        let cells = ids.len() * SHAPE_POINT;
+        if self.best.as_ref().is_some_and(|(best, _)| *best >= cells) {
+            return;
+        }
+        match self.program.objective {
+            Objective::Feasibility => {
+                self.stop_reason = Some("packing found".to_string());
+            }
+            Objective::MaxCells => {
+                println!(
+                    "Node {}: {} cells covered with {} pieces",
+                    self.nodes,
+                    cells,
+                    ids.len()
+                );
+                if cells == CELLS {
+                    self.stop_reason = Some("all cells covered".to_string());
+                }
+            }
+        }
+        self.best = Some((cells, ids));
+    }
+}
+
+/// Search the best packing of the program, `None` if there is none
+fn search(program: &Program, limits: &Limits) -> (Option<(usize, Vec<usize>)>, String, u64) {
+    let (mut search, problem) = BranchAndBound::new(program, limits);
+    match problem.solve() {
+        Ok(solution) => search.branch(solution),
+        Err(error) => search.stop_reason = Some(format!("relaxation: {}", error)),
+    }
+    let stop_reason = search
+        .stop_reason
+        .unwrap_or_else(|| "search space exhausted".to_string());
+    (search.best, stop_reason, search.nodes)
+}
+
+/// Save the LP file or print it if there is nothing to solve
+pub fn export(program: &Program, file: Option<&str>, solve: bool) -> Result<(), String> {
+    match file {
+        None if !solve => print!("{}", program.lp()),
+        None => {}
+        Some(file) => {
+            fs::write(file, program.lp()).map_err(|e| format!("{}: {}", file, e))?;
+            println!(
+                "LP with {} binary variables and {} constraints saved to {}",
+                program.ids.len(),
+                program.rows.len(),
+                file
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Search packings by branch and bound and print the best one with the statistics
+pub fn solve(program: &Program, limits: &Limits) {
+    let started = Instant::now();
+    let (best, stop_reason, nodes) = search(program, limits);
+    match &best {
+        None => println!("No packing found"),
+        Some((cells, ids)) => println!(
+            "Packing with {} cells: {}",
+            cells,
+            placements::printbox(ids)
+        ),
+    }
+    println!("Stop criterion: {}", stop_reason);
+    println!(
+        "ILP: nodes: {}, covered cells: {}, runtime: {:.3}s",
+        nodes,
+        best.map_or(0, |(cells, _)| cells),
+        started.elapsed().as_secs_f64()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lp_format() {
+        let program = Program::packing(Objective::Feasibility);
+        assert_eq!(program.rows.len(), CELLS + PIECES);
+        let lp = program.lp();
+        assert!(lp.starts_with("\\ p3d"));
+        assert!(lp.contains("\nSubject To\n cell_0_0_0: "));
+        assert!(lp.contains(" piece_y: x_y_"));
+        assert!(lp.contains(" x_h_4195"));
+        let e = placements::range(4).start;
+        assert_eq!(
+            variable(e),
+            format!("x_e_{}", placements::entry(e).combination)
+        );
+        assert!(lp.ends_with("\nEnd\n"));
+        assert!(lp.lines().all(|line| line.len() <= LINE_LENGTH));
+        let maximize = Program::packing(Objective::MaxCells).lp();
+        assert!(maximize.contains("Maximize\n obj: 5 x_a_"));
+        assert!(maximize.contains(" <= 1\n"));
+    }
+
+    #[test]
+    fn test_branch_and_bound() {
+        let ids: Vec<usize> = placements::range(0).chain(placements::range(24)).collect();
+        let limits = Limits::default();
+        let (best, _, _) = search(&Program::new(ids.clone(), Objective::MaxCells), &limits);
+        let (cells, placed) = best.unwrap();
+        assert_eq!(cells, 2 * SHAPE_POINT);
+        let masks: Vec<u128> = placed
+            .iter()
+            .map(|id| placements::entry(*id).mask)
+            .collect();
+        assert_eq!(masks[0] & masks[1], 0);
+        let (best, stop_reason, _) = search(&Program::new(ids, Objective::Feasibility), &limits);
+        assert_eq!(best, None);
+        assert!(stop_reason.starts_with("relaxation"));
+    }
+}
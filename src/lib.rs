@@ -1,6 +1,15 @@
+pub mod bounded;
+pub mod clique;
+pub mod cover;
 pub mod cube;
 pub mod evolution;
+pub mod ilp;
+pub mod limits;
 pub mod piece;
+pub mod placements;
+pub mod propagate;
+pub mod sat;
+pub mod solver;
 
 /// i2c (index to char)
 /// converts an index to a char for printing
@@ -21,6 +30,20 @@ pub fn i2c(index: isize) -> char {
     (index + 97) as char
 }
 
+/// c2i (char to index)
+/// converts a printed char back to an index
+///
+/// '.' is mapped to -1 and '#' to 25, other chars than 'a' to 'y' are invalid
+///
+pub fn c2i(c: char) -> Option<isize> {
+    match c {
+        '.' => Some(-1),
+        '#' => Some(25),
+        'a'..='y' => Some(c as isize - 97),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -38,4 +61,16 @@ mod tests {
         assert_eq!(i2c(15), 'p');
         assert_eq!(i2c(24), 'y');
     }
+
+    #[test]
+    fn test_c2i() {
+        assert_eq!(c2i('.'), Some(-1));
+        assert_eq!(c2i('#'), Some(25));
+        assert_eq!(c2i('a'), Some(0));
+        assert_eq!(c2i('y'), Some(24));
+        assert_eq!(c2i('z'), None);
+        for index in 0..25 {
+            assert_eq!(c2i(i2c(index)), Some(index));
+        }
+    }
 }
@@ -0,0 +1,64 @@
+//! Node and time limits of the exact searches
+//!
+//! The backtracker of `solver`, the branch and bound of `ilp` and the
+//! clique search count the nodes they visit and stop after the node limit
+//! or the time limit with the reason of the stop.
+
+use std::time::{Duration, Instant};
+
+/// Limits of a search, no limit if not set
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// stop after this number of visited nodes
+    pub nodes: Option<u64>,
+    /// wall clock limit in seconds
+    pub seconds: Option<u64>,
+}
+
+impl Limits {
+    /// Why to stop before node number `nodes`, none if it is within the limit
+    pub fn nodes_exceeded(&self, nodes: u64) -> Option<String> {
+        let limit = self.nodes?;
+        (nodes > limit).then(|| format!("node limit of {} reached", limit))
+    }
+
+    /// Why to stop a search started at `started`, none if there is time left
+    pub fn time_exceeded(&self, started: Instant) -> Option<String> {
+        let limit = Duration::from_secs(self.seconds?);
+        (started.elapsed() >= limit).then(|| format!("time limit of {:?} reached", limit))
+    }
+
+    /// The node limit or else the time limit that is exceeded
+    pub fn exceeded(&self, nodes: u64, started: Instant) -> Option<String> {
+        self.nodes_exceeded(nodes)
+            .or_else(|| self.time_exceeded(started))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeded() {
+        let started = Instant::now();
+        assert_eq!(Limits::default().exceeded(u64::MAX, started), None);
+        let limits = Limits {
+            nodes: Some(10),
+            seconds: None,
+        };
+        assert_eq!(limits.exceeded(10, started), None);
+        assert_eq!(
+            limits.exceeded(11, started),
+            Some("node limit of 10 reached".to_string())
+        );
+        let limits = Limits {
+            nodes: Some(10),
+            seconds: Some(0),
+        };
+        assert_eq!(
+            limits.exceeded(1, started),
+            Some("time limit of 0ns reached".to_string())
+        );
+    }
+}
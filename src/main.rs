@@ -1,11 +1,18 @@
 #[macro_use]
 extern crate clap;
 // use std::*;
-use clap::{App, Arg, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use p3d::clique;
+use p3d::cover;
 use p3d::cube;
 use p3d::piece;
+use p3d::propagate;
 
 use p3d::evolution;
+use p3d::ilp;
+use p3d::limits::Limits;
+use p3d::sat;
+use p3d::solver;
 
 // cSpell: disable
 
@@ -50,6 +57,672 @@ fn main() {
                         .long("population")
                         .help("Number of individums of the population: default 1000")
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .value_name("SECONDS")
+                        .help("Stop after the wall clock time has elapsed")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("stagnation")
+                        .long("stagnation")
+                        .value_name("N")
+                        .help("Stop if the best fitness did not improve for N generations")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("target-fitness")
+                        .long("target-fitness")
+                        .help("Stop as soon as this fitness is reached: default 125")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("diversity-threshold")
+                        .long("diversity-threshold")
+                        .help("React if the population diversity (0.0 .. 1.0) drops below")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("diversity-action")
+                        .long("diversity-action")
+                        .possible_values(&["reseed", "boost", "restart"])
+                        .help("Reaction on a low diversity: default reseed")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("reseed-ratio")
+                        .long("reseed-ratio")
+                        .help("Fraction of the population replaced on reseed: default 0.5")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("mutation-boost")
+                        .long("mutation-boost")
+                        .help("Factor the mutation rate is raised by on boost: default 4")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("elite")
+                        .long("elite")
+                        .help("Number of best distinct individuals kept on restart: default 10")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("diversity-cooldown")
+                        .long("diversity-cooldown")
+                        .help("Minimal number of generations between two reactions: default 10")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("mutation-rate")
+                        .long("mutation-rate")
+                        .help("Probability per gene to mutate: default 0.05")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("mutation-schedule")
+                        .long("mutation-schedule")
+                        .possible_values(&["fixed", "linear", "exponential", "one-fifth", "stagnation"])
+                        .help("How the mutation rate changes over the run: default fixed")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("final-mutation-rate")
+                        .long("final-mutation-rate")
+                        .help("Mutation rate at the generation limit of linear and exponential decay: default a tenth of the mutation rate")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("Seed for reproducible runs")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("islands")
+                        .short("i")
+                        .long("islands")
+                        .help("Number of populations evolving in parallel: default 1")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("island-mutation-rates")
+                        .long("island-mutation-rates")
+                        .value_name("RATES")
+                        .help("Comma separated mutation rates, one per island")
+                        .takes_value(true)
+                        .use_delimiter(true),
+                )
+                .arg(
+                    Arg::with_name("migration-interval")
+                        .long("migration-interval")
+                        .help("Generations between two migrations, 0 for none: default 25")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("migrants")
+                        .long("migrants")
+                        .help("Number of individuals migrating to the next island: default 5")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("hall-of-fame")
+                        .long("hall-of-fame")
+                        .value_name("N")
+                        .help("Keep the N best distinct placements of the run: default 0")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("modulo-symmetry")
+                        .long("modulo-symmetry")
+                        .help("Symmetric placements count as one in the hall of fame"),
+                )
+                .arg(
+                    Arg::with_name("hall-of-fame-file")
+                        .long("hall-of-fame-file")
+                        .value_name("FILE")
+                        .help("Save the hall of fame to FILE instead of printing it")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seed-population")
+                        .long("seed-population")
+                        .value_name("FILE")
+                        .help("Start with the placements of FILE (index lists or boxes)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("pin")
+                        .long("pin")
+                        .value_name("PINS")
+                        .help("Keep pieces at fixed combinations, e.g. a:0,h:4195")
+                        .validator(|pins| evolution::Pins::parse(&pins).map(|_| ()))
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("encoding")
+                        .long("encoding")
+                        .possible_values(&["placement", "permutation"])
+                        .help("Genome: combination per piece or order of the pieces: default placement")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("decoder")
+                        .long("decoder")
+                        .possible_values(&["configs", "cells"])
+                        .help("First fit of the permutation encoding tries configs in order or the ones with the lowest first cell first: default configs")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("order-crossover")
+                        .long("order-crossover")
+                        .possible_values(&["ox", "pmx"])
+                        .help("Crossover of the permutation encoding: default ox")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("order-mutation")
+                        .long("order-mutation")
+                        .possible_values(&["swap", "insert"])
+                        .help("Mutation of the permutation encoding: default swap")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("fitness-cache")
+                        .long("fitness-cache")
+                        .value_name("N")
+                        .help("Cache the fitness of N placements, 0 turns the cache off: default 0")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("repair")
+                        .long("repair")
+                        .help(
+                            "Repair mutated placements by constraint propagation, \
+                             placement encoding without --nsga2 only",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .value_name("N")
+                        .help("Threads that evaluate the fitness of a population: default number of CPUs")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("nsga2")
+                        .long("nsga2")
+                        .help("Optimize covered cells, overlaps and anchor violations with NSGA-II and print the Pareto front"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sweep")
+                .about("Run the genetic evolution for combinations of parameters and tabulate the results")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("generations")
+                        .short("g")
+                        .long("generations")
+                        .value_name("VALUES")
+                        .help("Generation limits as list or range START..END:STEP: default 1000")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("population")
+                        .short("p")
+                        .long("population")
+                        .value_name("VALUES")
+                        .help("Population sizes as list or range START..END:STEP: default 1000")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("mutation-rate")
+                        .long("mutation-rate")
+                        .value_name("VALUES")
+                        .help("Mutation rates as list or range START..END:STEP: default 0.05")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("mutation-schedule")
+                        .long("mutation-schedule")
+                        .value_name("SCHEDULES")
+                        .possible_values(&["fixed", "linear", "exponential", "one-fifth", "stagnation"])
+                        .help("Comma separated mutation schedules: default fixed")
+                        .takes_value(true)
+                        .use_delimiter(true),
+                )
+                .arg(
+                    Arg::with_name("seeds")
+                        .long("seeds")
+                        .help("Runs per configuration: default 5")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("Seed of the first run of a configuration, the others count up: default 0")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .short("j")
+                        .long("jobs")
+                        .help("Runs in parallel: default number of CPUs")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .value_name("N")
+                        .help("Threads that evaluate the fitness of a population: default number of CPUs")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("target-fitness")
+                        .long("target-fitness")
+                        .help("A run is successful if it reaches this fitness: default 125")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .value_name("SECONDS")
+                        .help("Stop a run after the wall clock time has elapsed")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("stagnation")
+                        .long("stagnation")
+                        .value_name("N")
+                        .help("Stop a run if the best fitness did not improve for N generations")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .possible_values(&["csv", "markdown"])
+                        .help("Format of the result table: default csv")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Save the result table to FILE instead of printing it")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("solve")
+                .about("Backtracking: search complete packings depth first")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("solutions")
+                        .long("solutions")
+                        .value_name("N")
+                        .help("Stop after N packings, w, x, y in name order, 0 searches all: default 1")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("node-limit")
+                        .long("node-limit")
+                        .value_name("N")
+                        .help("Stop after N visited nodes")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("no-prune")
+                        .long("no-prune")
+                        .help("Do not reject partial packings with empty regions that cannot be filled"),
+                )
+                .arg(
+                    Arg::with_name("propagate")
+                        .long("propagate")
+                        .help("Only try placements that constraint propagation keeps possible"),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .value_name("N")
+                        .help("Split the search into work units and search them on N threads")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("split-depth")
+                        .long("split-depth")
+                        .value_name("N")
+                        .help("Pieces placed in a work unit of the threads: default 3")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("transposition-table")
+                        .long("transposition-table")
+                        .value_name("MB")
+                        .help("Skip partial packings searched before, memory of the table: default 0, off")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .value_name("SECONDS")
+                        .help("Stop after the wall clock time has elapsed")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("estimate")
+                .about("Estimate the size of the backtracking tree with random probes")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("probes")
+                        .long("probes")
+                        .value_name("N")
+                        .help("Number of random root to leaf paths: default 10000")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("Seed of the random paths: default 0")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("no-prune")
+                        .long("no-prune")
+                        .help("Estimate the tree without pruning of empty regions that cannot be filled"),
+                )
+                .arg(
+                    Arg::with_name("propagate")
+                        .long("propagate")
+                        .help("Estimate the tree with constraint propagation of the placements"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("split")
+                .about("Write the work units of the backtracking search into numbered files")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .value_name("N")
+                        .help("Pieces placed in a work unit: default 3")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("files")
+                        .long("files")
+                        .value_name("N")
+                        .help("Spread the units over N files: default one file per unit")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("directory")
+                        .long("directory")
+                        .value_name("DIR")
+                        .help("Directory of the unit files: default the current directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("no-prune")
+                        .long("no-prune")
+                        .help("Do not reject partial packings with empty regions that cannot be filled"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("run-unit")
+                .about("Search all packings of the units of a work unit file")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The work unit file")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("The result file: default the unit file with extension .result")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("node-limit")
+                        .long("node-limit")
+                        .value_name("N")
+                        .help("Stop after N visited nodes")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .value_name("SECONDS")
+                        .help("Stop after the wall clock time has elapsed")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("no-prune")
+                        .long("no-prune")
+                        .help("Do not reject partial packings with empty regions that cannot be filled"),
+                )
+                .arg(
+                    Arg::with_name("propagate")
+                        .long("propagate")
+                        .help("Only try placements that constraint propagation keeps possible"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("merge")
+                .about("Sum up result files of work units and list the distinct solutions")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("FILES")
+                        .help("The result files")
+                        .required(true)
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Save the distinct solutions to FILE instead of printing them")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("simplify")
+                .about("Propagate pinned placements and print the remaining domains")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("pin")
+                        .long("pin")
+                        .value_name("PINS")
+                        .help("Placements to assign, e.g. a:0,h:4195")
+                        .validator(|pins| evolution::Pins::parse(&pins).map(|_| ()))
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export-sat")
+                .about("Write the packing problem as DIMACS CNF")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Save the CNF to FILE instead of printing it")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("at-most-one")
+                        .long("at-most-one")
+                        .possible_values(&["pairwise", "sequential"])
+                        .help("Encoding of at most one placement per cell and piece: default sequential")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("anchors")
+                        .long("anchors")
+                        .possible_values(&["variables", "clauses"])
+                        .help("Leave out placements off the anchors or add a clause per anchor: default variables")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import-sat")
+                .about("Print the box of the model a SAT solver found for an exported CNF")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("CNF")
+                        .help("The exported DIMACS file")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("MODEL")
+                        .help("The output of the SAT solver")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sat")
+                .about("Search packings with the embedded SAT solver on the CNF of export-sat")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("solutions")
+                        .long("solutions")
+                        .value_name("N")
+                        .help("Stop after N packings, 0 searches all: default 1")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("conflict-limit")
+                        .long("conflict-limit")
+                        .value_name("N")
+                        .help("Stop after N conflicts of the whole run")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .value_name("SECONDS")
+                        .help("Stop after the wall clock time has elapsed")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("at-most-one")
+                        .long("at-most-one")
+                        .possible_values(&["pairwise", "sequential"])
+                        .help("Encoding of at most one placement per cell and piece: default sequential")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("anchors")
+                        .long("anchors")
+                        .possible_values(&["variables", "clauses"])
+                        .help("Leave out placements off the anchors or add a clause per anchor: default variables")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export-cover")
+                .about("Write the packing problem as exact cover matrix for dancing links solvers")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Save the matrix to FILE instead of printing it")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("legend")
+                        .long("legend")
+                        .value_name("FILE")
+                        .help("Save the row legend to FILE instead of comments in the matrix")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("secondary")
+                        .long("secondary")
+                        .possible_values(&["none", "cells", "pieces"])
+                        .help("Columns covered at most once instead of exactly once: default none")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ilp")
+                .about("Write the packing problem as 0/1 program in the CPLEX LP format or solve it")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Save the LP to FILE instead of printing it")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("objective")
+                        .long("objective")
+                        .possible_values(&["feasibility", "max-cells"])
+                        .help("Search a complete packing or a partial one with the most covered cells: default feasibility")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("solve")
+                        .long("solve")
+                        .help("Solve the program by branch and bound on the LP relaxations"),
+                )
+                .arg(
+                    Arg::with_name("node-limit")
+                        .long("node-limit")
+                        .value_name("N")
+                        .help("Stop after N relaxations")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .value_name("SECONDS")
+                        .help("Stop after the wall clock time has elapsed")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("clique")
+                .about("Write the placement compatibility graph in the DIMACS format or search its maximum clique")
+                .version("1.0")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Save the graph to FILE instead of printing it")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("solve")
+                        .long("solve")
+                        .help("Search the maximum clique with coloring bounds"),
+                )
+                .arg(
+                    Arg::with_name("node-limit")
+                        .long("node-limit")
+                        .value_name("N")
+                        .help("Stop after N visited nodes")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .value_name("SECONDS")
+                        .help("Stop after the wall clock time has elapsed")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
@@ -101,7 +774,7 @@ fn main() {
         0 => {}
         1 => println!("Some verbose info"),
         2 => println!("Tons of verbose info"),
-        3 | _ => println!("Don't be crazy"),
+        _ => println!("Don't be crazy"),
     }
 
     // You can handle information about subcommands by requesting their matches by name
@@ -129,14 +802,12 @@ fn main() {
             mypiece.set_combination(Some(index));
             mypiece.set_piece();
         }
-        if matches.is_present("next") {
-            if !mypiece.is_config() {
-                while !mypiece.is_config() {
-                    mypiece.next_config();
-                }
-                println!("Use fitting index: {}", mypiece.get_combination());
-                mypiece.set_piece();
+        if matches.is_present("next") && !mypiece.is_config() {
+            while !mypiece.is_config() {
+                mypiece.next_config();
             }
+            println!("Use fitting index: {}", mypiece.get_combination());
+            mypiece.set_piece();
         }
         if mypiece.fit_in_box() {
             mypiece.add_to_box(&mut mybox);
@@ -164,14 +835,338 @@ fn main() {
         println!("Number of variations: {}", num_of_variations);
     }
     if let Some(matches) = matches.subcommand_matches("ge") {
-        let mut generations: u64 = 1000;
+        let mut settings = evolution::Settings::default();
         if matches.is_present("generations") {
-            generations = value_t!(matches, "generations", u64).unwrap();
+            settings.generations = value_t!(matches, "generations", u64).unwrap();
         }
-        let mut population: usize = 1000;
         if matches.is_present("population") {
-            population = value_t!(matches, "population", usize).unwrap();
+            settings.population = value_t!(matches, "population", usize).unwrap();
+        }
+        if matches.is_present("time-limit") {
+            settings.time_limit = Some(value_t!(matches, "time-limit", u64).unwrap());
+        }
+        if matches.is_present("stagnation") {
+            settings.stagnation = Some(value_t!(matches, "stagnation", u64).unwrap());
+        }
+        if matches.is_present("target-fitness") {
+            settings.target_fitness = Some(value_t!(matches, "target-fitness", usize).unwrap());
+        }
+        if matches.is_present("diversity-threshold") {
+            settings.diversity_threshold =
+                Some(value_t!(matches, "diversity-threshold", f64).unwrap());
+        }
+        if matches.is_present("diversity-action") {
+            settings.diversity_action =
+                value_t!(matches, "diversity-action", evolution::DiversityAction).unwrap();
+        }
+        if matches.is_present("reseed-ratio") {
+            settings.reseed_ratio = value_t!(matches, "reseed-ratio", f64).unwrap();
+        }
+        if matches.is_present("mutation-boost") {
+            settings.mutation_boost = value_t!(matches, "mutation-boost", f64).unwrap();
+        }
+        if matches.is_present("elite") {
+            settings.elite = value_t!(matches, "elite", usize).unwrap();
+        }
+        if matches.is_present("diversity-cooldown") {
+            settings.diversity_cooldown = value_t!(matches, "diversity-cooldown", u64).unwrap();
+        }
+        if matches.is_present("mutation-rate") {
+            settings.mutation_rate = value_t!(matches, "mutation-rate", f64).unwrap();
+        }
+        if matches.is_present("mutation-schedule") {
+            settings.mutation_schedule =
+                value_t!(matches, "mutation-schedule", evolution::MutationSchedule).unwrap();
+        }
+        if matches.is_present("final-mutation-rate") {
+            settings.final_mutation_rate =
+                Some(value_t!(matches, "final-mutation-rate", f64).unwrap());
+        }
+        if matches.is_present("seed") {
+            settings.seed = Some(value_t!(matches, "seed", u64).unwrap());
+        }
+        if matches.is_present("islands") {
+            settings.islands = value_t!(matches, "islands", usize).unwrap();
+        }
+        if matches.is_present("island-mutation-rates") {
+            settings.island_mutation_rates =
+                values_t!(matches, "island-mutation-rates", f64).unwrap();
+        }
+        if matches.is_present("migration-interval") {
+            settings.migration_interval = value_t!(matches, "migration-interval", u64).unwrap();
+        }
+        if matches.is_present("migrants") {
+            settings.migrants = value_t!(matches, "migrants", usize).unwrap();
+        }
+        if matches.is_present("hall-of-fame") {
+            settings.hall_of_fame = value_t!(matches, "hall-of-fame", usize).unwrap();
+        }
+        settings.modulo_symmetry = matches.is_present("modulo-symmetry");
+        settings.nsga2 = matches.is_present("nsga2");
+        settings.repair = matches.is_present("repair");
+        if matches.is_present("fitness-cache") {
+            settings.fitness_cache = value_t!(matches, "fitness-cache", usize).unwrap();
+        }
+        if matches.is_present("threads") {
+            settings.threads = Some(value_t!(matches, "threads", usize).unwrap());
+        }
+        if let Some(file) = matches.value_of("hall-of-fame-file") {
+            settings.hall_of_fame_file = Some(file.to_string());
+        }
+        if let Some(file) = matches.value_of("seed-population") {
+            settings.seed_population = Some(file.to_string());
+        }
+        if let Some(pins) = matches.value_of("pin") {
+            settings.pins = evolution::Pins::parse(pins).unwrap();
+        }
+        if matches.is_present("encoding") {
+            settings.encoding = value_t!(matches, "encoding", evolution::Encoding).unwrap();
+        }
+        if matches.is_present("decoder") {
+            settings.decoder = value_t!(matches, "decoder", evolution::Decoder).unwrap();
+        }
+        if matches.is_present("order-crossover") {
+            settings.order_crossover =
+                value_t!(matches, "order-crossover", evolution::OrderCrossover).unwrap();
+        }
+        if matches.is_present("order-mutation") {
+            settings.order_mutation =
+                value_t!(matches, "order-mutation", evolution::OrderMutation).unwrap();
+        }
+        evolution::solve_cube(&settings);
+    }
+    if let Some(matches) = matches.subcommand_matches("sweep") {
+        let mut sweep = evolution::Sweep::default();
+        let values = |name: &str| matches.value_of(name).map(evolution::parse_values);
+        let counts = |name: &str| matches.value_of(name).map(evolution::parse_counts);
+        match counts("generations") {
+            Some(Ok(generations)) => sweep.generations = generations,
+            Some(Err(error)) => return println!("Invalid generations: {}", error),
+            None => {}
+        }
+        match counts("population") {
+            Some(Ok(populations)) => {
+                sweep.populations = populations.iter().map(|p| *p as usize).collect()
+            }
+            Some(Err(error)) => return println!("Invalid population: {}", error),
+            None => {}
+        }
+        match values("mutation-rate") {
+            Some(Ok(rates)) => sweep.mutation_rates = rates,
+            Some(Err(error)) => return println!("Invalid mutation rate: {}", error),
+            None => {}
+        }
+        if matches.is_present("mutation-schedule") {
+            sweep.mutation_schedules =
+                values_t!(matches, "mutation-schedule", evolution::MutationSchedule).unwrap();
+        }
+        if matches.is_present("seeds") {
+            sweep.seeds = value_t!(matches, "seeds", usize).unwrap();
+        }
+        if matches.is_present("seed") {
+            sweep.settings.seed = Some(value_t!(matches, "seed", u64).unwrap());
+        }
+        if matches.is_present("jobs") {
+            sweep.jobs = value_t!(matches, "jobs", usize).unwrap();
+        }
+        if matches.is_present("threads") {
+            sweep.settings.threads = Some(value_t!(matches, "threads", usize).unwrap());
         }
-        evolution::solve_cube(generations, population);
+        if matches.is_present("target-fitness") {
+            sweep.settings.target_fitness =
+                Some(value_t!(matches, "target-fitness", usize).unwrap());
+        }
+        if matches.is_present("time-limit") {
+            sweep.settings.time_limit = Some(value_t!(matches, "time-limit", u64).unwrap());
+        }
+        if matches.is_present("stagnation") {
+            sweep.settings.stagnation = Some(value_t!(matches, "stagnation", u64).unwrap());
+        }
+        if matches.is_present("format") {
+            sweep.format = value_t!(matches, "format", evolution::TableFormat).unwrap();
+        }
+        if let Some(file) = matches.value_of("output") {
+            sweep.output = Some(file.to_string());
+        }
+        evolution::sweep(&sweep);
+    }
+    if let Some(matches) = matches.subcommand_matches("solve") {
+        let mut settings = solver::Settings::default();
+        if matches.is_present("solutions") {
+            settings.solutions = value_t!(matches, "solutions", usize).unwrap();
+        }
+        settings.limits = limits(matches);
+        settings.prune = !matches.is_present("no-prune");
+        settings.propagate = matches.is_present("propagate");
+        if matches.is_present("threads") {
+            settings.threads = Some(value_t!(matches, "threads", usize).unwrap());
+        }
+        if matches.is_present("split-depth") {
+            settings.split_depth = value_t!(matches, "split-depth", usize).unwrap();
+        }
+        if matches.is_present("transposition-table") {
+            settings.transposition_memory =
+                value_t!(matches, "transposition-table", usize).unwrap();
+        }
+        solver::solve(&settings);
+    }
+    if let Some(matches) = matches.subcommand_matches("split") {
+        let mut settings = solver::Settings::default();
+        if matches.is_present("depth") {
+            settings.split_depth = value_t!(matches, "depth", usize).unwrap();
+        }
+        settings.prune = !matches.is_present("no-prune");
+        let files = if matches.is_present("files") {
+            Some(value_t!(matches, "files", usize).unwrap())
+        } else {
+            None
+        };
+        let directory = matches.value_of("directory").unwrap_or(".");
+        if let Err(error) = solver::write_units(&settings, directory, files) {
+            println!("Cannot write work units: {}", error);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("run-unit") {
+        let mut settings = solver::Settings {
+            solutions: 0,
+            ..solver::Settings::default()
+        };
+        settings.limits = limits(matches);
+        settings.prune = !matches.is_present("no-prune");
+        settings.propagate = matches.is_present("propagate");
+        let file = matches.value_of("FILE").unwrap();
+        let output = match matches.value_of("output") {
+            Some(output) => output.to_string(),
+            None => std::path::Path::new(file)
+                .with_extension("result")
+                .display()
+                .to_string(),
+        };
+        if let Err(error) = solver::run_unit(&settings, file, &output) {
+            println!("Cannot run work unit: {}", error);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("merge") {
+        let files: Vec<&str> = matches.values_of("FILES").unwrap().collect();
+        if let Err(error) = solver::merge(&files, matches.value_of("output")) {
+            println!("Cannot merge results: {}", error);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("estimate") {
+        let settings = solver::Settings {
+            prune: !matches.is_present("no-prune"),
+            propagate: matches.is_present("propagate"),
+            ..solver::Settings::default()
+        };
+        let probes = if matches.is_present("probes") {
+            value_t!(matches, "probes", usize).unwrap()
+        } else {
+            10000
+        };
+        let seed = if matches.is_present("seed") {
+            value_t!(matches, "seed", u64).unwrap()
+        } else {
+            0
+        };
+        solver::estimate(&settings, probes, seed);
+    }
+    if let Some(matches) = matches.subcommand_matches("simplify") {
+        let pins = evolution::Pins::parse(matches.value_of("pin").unwrap_or("")).unwrap();
+        let ids: Vec<usize> = pins.genes().iter().map(|gene| gene.id()).collect();
+        if let Err(error) = propagate::simplify(&ids) {
+            println!("Conflict: {}", error);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("export-sat") {
+        let at_most_one = if matches.is_present("at-most-one") {
+            value_t!(matches, "at-most-one", sat::AtMostOne).unwrap()
+        } else {
+            sat::AtMostOne::Sequential
+        };
+        let anchors = if matches.is_present("anchors") {
+            value_t!(matches, "anchors", sat::Anchors).unwrap()
+        } else {
+            sat::Anchors::Variables
+        };
+        if let Err(error) = sat::export(matches.value_of("output"), at_most_one, anchors) {
+            println!("Cannot export CNF: {}", error);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("import-sat") {
+        let cnf = matches.value_of("CNF").unwrap();
+        let model = matches.value_of("MODEL").unwrap();
+        if let Err(error) = sat::import(cnf, model) {
+            println!("Cannot import model: {}", error);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("sat") {
+        let mut settings = sat::Settings::default();
+        if matches.is_present("solutions") {
+            settings.solutions = value_t!(matches, "solutions", usize).unwrap();
+        }
+        if matches.is_present("conflict-limit") {
+            settings.conflict_limit = Some(value_t!(matches, "conflict-limit", u64).unwrap());
+        }
+        if matches.is_present("time-limit") {
+            settings.time_limit = Some(value_t!(matches, "time-limit", u64).unwrap());
+        }
+        if matches.is_present("at-most-one") {
+            settings.at_most_one = value_t!(matches, "at-most-one", sat::AtMostOne).unwrap();
+        }
+        if matches.is_present("anchors") {
+            settings.anchors = value_t!(matches, "anchors", sat::Anchors).unwrap();
+        }
+        sat::solve(&settings);
+    }
+    if let Some(matches) = matches.subcommand_matches("export-cover") {
+        let secondary = if matches.is_present("secondary") {
+            value_t!(matches, "secondary", cover::Secondary).unwrap()
+        } else {
+            cover::Secondary::None
+        };
+        if let Err(error) = cover::export(
+            matches.value_of("output"),
+            matches.value_of("legend"),
+            secondary,
+        ) {
+            println!("Cannot export exact cover matrix: {}", error);
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("ilp") {
+        let objective = if matches.is_present("objective") {
+            value_t!(matches, "objective", ilp::Objective).unwrap()
+        } else {
+            ilp::Objective::Feasibility
+        };
+        let program = ilp::Program::packing(objective);
+        let solve = matches.is_present("solve");
+        if let Err(error) = ilp::export(&program, matches.value_of("output"), solve) {
+            println!("Cannot export LP: {}", error);
+        }
+        if solve {
+            ilp::solve(&program, &limits(matches));
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("clique") {
+        let graph = clique::Graph::packing();
+        let solve = matches.is_present("solve");
+        if let Err(error) = clique::export(&graph, matches.value_of("output"), solve) {
+            println!("Cannot export graph: {}", error);
+        }
+        if solve {
+            clique::solve(&graph, &limits(matches));
+        }
+    }
+}
+
+/// The node and time limits of the arguments `--node-limit` and `--time-limit`
+fn limits(matches: &ArgMatches) -> Limits {
+    Limits {
+        nodes: matches
+            .is_present("node-limit")
+            .then(|| value_t!(matches, "node-limit", u64).unwrap()),
+        seconds: matches
+            .is_present("time-limit")
+            .then(|| value_t!(matches, "time-limit", u64).unwrap()),
     }
 }
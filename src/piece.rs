@@ -39,8 +39,8 @@ use super::cube;
 use crate::cube::AddToBox;
 
 const ROTATIONS: usize = 24;
-const SHAPE_POINT: usize = 5;
-const DIMENSIONS: usize = 3;
+pub const SHAPE_POINT: usize = 5;
+pub const DIMENSIONS: usize = 3;
 
 pub const PIECES: usize = 25;
 
@@ -183,22 +183,36 @@ impl Piece {
     /// returns true if it is the last configuration
     /// otherwise false
     pub fn next_config(self: &mut Piece) -> bool {
-        self.rotation = self.rotation + 1;
+        self.rotation += 1;
         if self.rotation >= ROTATIONS {
             self.rotation = 0;
-            self.x = self.x + 1;
+            self.x += 1;
         }
         if self.x >= cube::LENGTH as isize {
             self.x = 0;
-            self.y = self.y + 1;
+            self.y += 1;
         }
         if self.y >= cube::WIDTH as isize {
             self.y = 0;
-            self.z = self.z + 1;
+            self.z += 1;
         }
         self.z != cube::HEIGHT as isize
     }
 
+    /// move to the next valid configuration
+    ///
+    /// after the last valid configuration it wraps around to the first one
+    pub fn next_valid_config(self: &mut Piece) {
+        loop {
+            if !self.next_config() {
+                self.set_combination(None);
+            }
+            if self.is_config() {
+                break;
+            }
+        }
+    }
+
     /// checks if a piece fits into a box
     ///
     /// returns true if it fits, otherwise false
@@ -221,14 +235,13 @@ impl Piece {
     ///
     /// return true if it fits otherwise false
     pub fn fit_to_position(self: &Piece) -> bool {
-        if self.name_index > 21 {
-            // piece 22 w; 23 x and 24 y fit everywhere
+        if is_free(self.name_index) {
             return true;
         }
         for i in 0..SHAPE_POINT {
-            let fit = self.piece[i][0] == FIXED_MAP[self.name_index as usize][0];
-            let fit = fit & (self.piece[i][1] == FIXED_MAP[self.name_index as usize][1]);
-            let fit = fit & (self.piece[i][2] == FIXED_MAP[self.name_index as usize][2]);
+            let fit = self.piece[i][0] == FIXED_MAP[self.name_index][0];
+            let fit = fit & (self.piece[i][1] == FIXED_MAP[self.name_index][1]);
+            let fit = fit & (self.piece[i][2] == FIXED_MAP[self.name_index][2]);
             if fit {
                 return true;
             }
@@ -237,10 +250,10 @@ impl Piece {
     }
 
     pub fn set_piece(self: &mut Piece) -> &mut Piece {
-        for i in 0..SHAPE_POINT {
-            self.piece[i][0] = ROT_MAP[self.rotation as usize][i][0] + self.x;
-            self.piece[i][1] = ROT_MAP[self.rotation as usize][i][1] + self.y;
-            self.piece[i][2] = ROT_MAP[self.rotation as usize][i][2] + self.z;
+        for (point, shape) in self.piece.iter_mut().zip(ROT_MAP[self.rotation].iter()) {
+            point[0] = shape[0] + self.x;
+            point[1] = shape[1] + self.y;
+            point[2] = shape[2] + self.z;
         }
         self
     }
@@ -269,6 +282,18 @@ impl Piece {
         self.fit_in_box() && self.fit_to_position()
     }
 
+    /// the positions occupied by the piece, as set by `set_piece`
+    pub fn get_cells(self: &Piece) -> &[[isize; DIMENSIONS]; SHAPE_POINT] {
+        &self.piece
+    }
+
+    /// the cells of the piece as bit set, cell (x, y, z) is bit x * 25 + y * 5 + z
+    pub fn cell_mask(self: &Piece) -> u128 {
+        self.piece
+            .iter()
+            .fold(0, |mask, position| mask | 1 << cube::cell(*position))
+    }
+
     pub fn get_name(self: &Piece) -> usize {
         self.name_index
     }
@@ -281,6 +306,37 @@ impl Piece {
     }
 }
 
+/// Piece 22 w, 23 x and 24 y have no anchor and fit everywhere
+pub fn is_free(name: usize) -> bool {
+    name > 21
+}
+
+/// The cell of FIXED_MAP a piece has to cover, None for the free pieces
+pub fn anchor(name: usize) -> Option<[isize; DIMENSIONS]> {
+    if is_free(name) {
+        None
+    } else {
+        Some(FIXED_MAP[name])
+    }
+}
+
+/// All combinations that place a piece validly into the box
+///
+/// The combinations are in the order of `next_config`.
+pub fn valid_combinations(name: usize) -> Vec<u16> {
+    let mut p = Piece::new(name);
+    let mut combinations = Vec::new();
+    loop {
+        if p.is_config() {
+            combinations.push(p.get_combination());
+        }
+        if !p.next_config() {
+            break;
+        }
+    }
+    combinations
+}
+
 impl PartialOrd for Piece {
     fn partial_cmp(&self, other: &Piece) -> Option<Ordering> {
         if self.name_index == other.name_index {
@@ -330,6 +386,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_valid_combinations() {
+        // corner 'a' at the origin
+        let combinations = valid_combinations(0);
+        assert_eq!(combinations[0], 0);
+        assert_eq!(combinations.len(), 12);
+        // free piece 'y'
+        assert_eq!(valid_combinations(24).len(), 960);
+        for name in 0..PIECES {
+            for combination in valid_combinations(name) {
+                let mut p = Piece::new(name);
+                p.set_combination(Some(combination));
+                assert!(p.is_config());
+            }
+        }
+    }
+
+    #[test]
+    fn test_next_valid_config_wraps_around() {
+        let combinations = valid_combinations(7);
+        let mut p = Piece::new(7);
+        p.set_combination(combinations.last().copied());
+        p.next_valid_config();
+        assert_eq!(p.get_combination(), combinations[0]);
+        p.next_valid_config();
+        assert_eq!(p.get_combination(), combinations[1]);
+    }
+
+    #[test]
+    fn test_cell_mask() {
+        let mut p = Piece::new(24);
+        p.set_combination(valid_combinations(24).last().copied());
+        p.set_piece();
+        let mask = p.cell_mask();
+        assert_eq!(mask.count_ones(), 5);
+        for cell in p.get_cells() {
+            assert_ne!(mask & 1 << (cell[0] * 25 + cell[1] * 5 + cell[2]), 0);
+        }
+    }
+
     #[test]
     fn test_clone_it() {
         let p_orig = Piece::new(1);
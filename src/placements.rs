@@ -0,0 +1,186 @@
+//! Lookup table of all valid placements of all pieces
+//!
+//! Every valid configuration of every piece gets a dense id. The ids of a
+//! piece are consecutive and in the order of `Piece::next_config`, the
+//! pieces follow each other by name. The table is built once on first use.
+
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use super::cube::{PrintBox, CELLS};
+use super::piece::{valid_combinations, Piece, DIMENSIONS, PIECES, SHAPE_POINT};
+use super::{c2i, i2c};
+
+/// A valid placement of a piece
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: usize,
+    pub combination: u16,
+    pub cells: [[isize; DIMENSIONS]; SHAPE_POINT],
+    /// the cells as bit set, see `Piece::cell_mask`
+    pub mask: u128,
+}
+
+struct Table {
+    entries: Vec<Entry>,
+    /// first id of every piece and the total count at the end
+    starts: Vec<usize>,
+    /// per cell the ids of the placements that cover it
+    covering: Vec<Vec<usize>>,
+}
+
+fn table() -> &'static Table {
+    static TABLE: OnceLock<Table> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut entries = Vec::new();
+        let mut starts = Vec::with_capacity(PIECES + 1);
+        for name in 0..PIECES {
+            starts.push(entries.len());
+            for combination in valid_combinations(name) {
+                let mut p = Piece::new(name);
+                p.set_combination(Some(combination));
+                p.set_piece();
+                entries.push(Entry {
+                    name,
+                    combination,
+                    cells: *p.get_cells(),
+                    mask: p.cell_mask(),
+                });
+            }
+        }
+        starts.push(entries.len());
+        let mut covering = vec![Vec::new(); CELLS];
+        for (id, entry) in entries.iter().enumerate() {
+            for (cell, ids) in covering.iter_mut().enumerate() {
+                if entry.mask & 1 << cell != 0 {
+                    ids.push(id);
+                }
+            }
+        }
+        Table {
+            entries,
+            starts,
+            covering,
+        }
+    })
+}
+
+/// All valid placements, ordered by piece
+pub fn entries() -> &'static [Entry] {
+    &table().entries
+}
+
+pub fn entry(id: usize) -> &'static Entry {
+    &table().entries[id]
+}
+
+/// The ids of the valid placements of a piece
+pub fn range(name: usize) -> Range<usize> {
+    let starts = &table().starts;
+    starts[name]..starts[name + 1]
+}
+
+/// The ids of the placements that cover a cell, cell numbers as in `Entry::mask`
+pub fn covering(cell: usize) -> &'static [usize] {
+    &table().covering[cell]
+}
+
+/// The id of a combination, if it is a valid configuration of the piece
+pub fn id(name: usize, combination: u16) -> Option<usize> {
+    let range = range(name);
+    entries()[range.clone()]
+        .iter()
+        .position(|e| e.combination == combination)
+        .map(|position| range.start + position)
+}
+
+/// A piece name as letter 'a' to 'y' or number 0 to 24
+pub fn piece(name: &str) -> Result<usize, String> {
+    let mut chars = name.chars();
+    let index = match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_lowercase() => c2i(c),
+        _ => name.parse::<isize>().ok(),
+    };
+    match index {
+        Some(index) if (0..PIECES as isize).contains(&index) => Ok(index as usize),
+        _ => Err(format!("invalid piece {}", name)),
+    }
+}
+
+/// The id of a combination of a piece, which must be a valid placement
+pub fn placed(name: usize, combination: &str) -> Result<usize, String> {
+    let combination = combination
+        .parse::<u16>()
+        .map_err(|_| format!("invalid combination {}", combination))?;
+    id(name, combination).ok_or_else(|| {
+        format!(
+            "combination {} of piece {} is no valid placement",
+            combination,
+            i2c(name as isize)
+        )
+    })
+}
+
+/// The id of a `name:combination` placement, e.g. `h:4195`
+///
+/// This is how `ge` pins pieces and reads seeds and how `solve` writes
+/// work units and their results.
+pub fn parse(token: &str) -> Result<usize, String> {
+    let (name, combination) = token
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| format!("{} is not PIECE:COMBINATION", token))?;
+    placed(piece(name)?, combination)
+}
+
+/// The box of placement ids
+pub fn printbox(ids: &[usize]) -> PrintBox {
+    let mut b = PrintBox::new();
+    for id in ids {
+        let entry = entry(*id);
+        b.add_mask(entry.mask, entry.name);
+    }
+    b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids() {
+        assert_eq!(range(0), 0..12);
+        assert_eq!(range(24).len(), 960);
+        assert!(entries().len() < 1 << 15);
+        for name in [0, 7, 24] {
+            for (id, combination) in range(name).zip(valid_combinations(name)) {
+                assert_eq!(entry(id).name, name);
+                assert_eq!(self::id(name, combination), Some(id));
+            }
+        }
+        assert_eq!(id(1, 0), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("a:0"), Ok(0));
+        assert_eq!(parse(" h:4195"), Ok(id(7, 4195).unwrap()));
+        assert_eq!(parse("7:4195"), parse("h:4195"));
+        assert_eq!(
+            parse("b:0"),
+            Err("combination 0 of piece b is no valid placement".to_string())
+        );
+        assert_eq!(parse("z:0"), Err("invalid piece z".to_string()));
+        assert_eq!(parse("a:x"), Err("invalid combination x".to_string()));
+        assert_eq!(parse("a"), Err("a is not PIECE:COMBINATION".to_string()));
+    }
+
+    #[test]
+    fn test_covering() {
+        let total: usize = (0..CELLS).map(|cell| covering(cell).len()).sum();
+        assert_eq!(total, entries().len() * SHAPE_POINT);
+        for id in covering(62) {
+            assert_ne!(entry(*id).mask & 1 << 62, 0);
+        }
+    }
+}
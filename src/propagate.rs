@@ -0,0 +1,323 @@
+//! Constraint propagation over the domains of the placements
+//!
+//! Every piece has the domain of its placements that are still possible
+//! and every cell the placements that can still cover it. Assigning a
+//! placement removes the other placements of the piece and all placements
+//! that overlap it. Then unit propagation runs to the fixpoint: a cell
+//! that only one placement can cover forces that placement, a piece with
+//! only one placement left is placed there. A cell or a piece without any
+//! placement left is a conflict.
+//!
+//! Every change goes on a trail, so a backtracking search takes a `mark`
+//! before a decision and `undo`es back to it. `simplify` propagates pinned
+//! placements on their own, `solver` uses the domains to narrow its
+//! candidates and the repair of the evolution keeps the genes that are
+//! consistent with the propagation.
+
+use crate::cube::{cell_name, PrintBox, CELLS};
+use crate::piece::PIECES;
+use crate::{i2c, placements};
+
+/// A change of the domains
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Change {
+    Removed(usize),
+    Assigned(usize),
+}
+
+/// The domains of the pieces and cells
+#[derive(Clone, Debug)]
+pub struct Domains {
+    /// per placement id: still possible
+    alive: Vec<bool>,
+    /// per piece: number of possible placements
+    pieces: [usize; PIECES],
+    /// per cell: number of possible placements that cover it
+    cells: [usize; CELLS],
+    /// placement ids assigned by decisions and by propagation
+    assigned: Vec<usize>,
+    occupied: u128,
+    used: u32,
+    trail: Vec<Change>,
+}
+
+impl Default for Domains {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Domains {
+    /// All valid placements are possible
+    pub fn new() -> Self {
+        let mut pieces = [0; PIECES];
+        for (name, count) in pieces.iter_mut().enumerate() {
+            *count = placements::range(name).len();
+        }
+        let mut cells = [0; CELLS];
+        for (cell, count) in cells.iter_mut().enumerate() {
+            *count = placements::covering(cell).len();
+        }
+        Domains {
+            alive: vec![true; placements::entries().len()],
+            pieces,
+            cells,
+            assigned: Vec::with_capacity(PIECES),
+            occupied: 0,
+            used: 0,
+            trail: Vec::new(),
+        }
+    }
+
+    pub fn is_alive(&self, id: usize) -> bool {
+        self.alive[id]
+    }
+
+    /// Number of possible placements of a piece
+    pub fn size(&self, name: usize) -> usize {
+        self.pieces[name]
+    }
+
+    /// Number of possible placements that cover a cell
+    pub fn covers(&self, cell: usize) -> usize {
+        self.cells[cell]
+    }
+
+    /// The placement ids assigned so far, in order
+    pub fn assigned(&self) -> &[usize] {
+        &self.assigned
+    }
+
+    /// Number of possible placements
+    pub fn alive_count(&self) -> usize {
+        self.pieces.iter().sum()
+    }
+
+    /// The position of the trail to undo to
+    pub fn mark(&self) -> usize {
+        self.trail.len()
+    }
+
+    /// Take back all changes after the mark
+    pub fn undo(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            match self.trail.pop().unwrap() {
+                Change::Removed(id) => {
+                    let entry = placements::entry(id);
+                    self.alive[id] = true;
+                    self.pieces[entry.name] += 1;
+                    for cell in cells(entry.mask) {
+                        self.cells[cell] += 1;
+                    }
+                }
+                Change::Assigned(id) => {
+                    let entry = placements::entry(id);
+                    self.occupied &= !entry.mask;
+                    self.used &= !(1 << entry.name);
+                    self.assigned.pop();
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, id: usize) {
+        let entry = placements::entry(id);
+        self.alive[id] = false;
+        self.pieces[entry.name] -= 1;
+        for cell in cells(entry.mask) {
+            self.cells[cell] -= 1;
+        }
+        self.trail.push(Change::Removed(id));
+    }
+
+    /// The placement a unit piece or cell is forced to, or the conflict
+    fn unit(
+        &self,
+        mut ids: impl Iterator<Item = usize>,
+        count: usize,
+        what: String,
+    ) -> Result<Option<usize>, String> {
+        match count {
+            0 => Err(format!("{} has no placement left", what)),
+            1 => Ok(ids.find(|id| self.alive[*id])),
+            _ => Ok(None),
+        }
+    }
+
+    /// The forced placement of a piece, if the piece is unused
+    fn piece_unit(&self, name: usize) -> Result<Option<usize>, String> {
+        if self.used & 1 << name != 0 {
+            return Ok(None);
+        }
+        let what = format!("piece {}", i2c(name as isize));
+        self.unit(placements::range(name), self.pieces[name], what)
+    }
+
+    /// The forced placement of a cell, if the cell is empty
+    fn cell_unit(&self, cell: usize) -> Result<Option<usize>, String> {
+        if self.occupied & 1 << cell != 0 {
+            return Ok(None);
+        }
+        let what = format!("cell {}", cell_name(cell));
+        let covering = placements::covering(cell).iter().copied();
+        self.unit(covering, self.cells[cell], what)
+    }
+
+    /// Assign a placement and propagate, the conflict if there is one
+    ///
+    /// On a conflict the domains are left half way, `undo` to a mark
+    /// taken before.
+    pub fn assign(&mut self, id: usize) -> Result<(), String> {
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let entry = placements::entry(id);
+            if self.assigned.contains(&id) {
+                continue;
+            }
+            if !self.alive[id] {
+                return Err(format!(
+                    "{}:{} is not possible",
+                    i2c(entry.name as isize),
+                    entry.combination
+                ));
+            }
+            self.occupied |= entry.mask;
+            self.used |= 1 << entry.name;
+            self.assigned.push(id);
+            self.trail.push(Change::Assigned(id));
+            let mut removed: Vec<usize> = placements::range(entry.name)
+                .filter(|other| *other != id && self.alive[*other])
+                .collect();
+            for cell in cells(entry.mask) {
+                removed.extend(
+                    placements::covering(cell)
+                        .iter()
+                        .filter(|other| **other != id && self.alive[**other]),
+                );
+            }
+            removed.sort_unstable();
+            removed.dedup();
+            for other in &removed {
+                self.remove(*other);
+            }
+            for other in removed {
+                let entry = placements::entry(other);
+                pending.extend(self.piece_unit(entry.name)?);
+                for cell in cells(entry.mask) {
+                    pending.extend(self.cell_unit(cell)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Propagate the units of the initial domains
+    pub fn settle(&mut self) -> Result<(), String> {
+        let mut forced = Vec::new();
+        for name in 0..PIECES {
+            forced.extend(self.piece_unit(name)?);
+        }
+        for cell in 0..CELLS {
+            forced.extend(self.cell_unit(cell)?);
+        }
+        for id in forced {
+            self.assign(id)?;
+        }
+        Ok(())
+    }
+
+    /// The box of the assigned placements
+    pub fn printbox(&self) -> PrintBox {
+        placements::printbox(&self.assigned)
+    }
+}
+
+/// The cells of a mask
+fn cells(mut mask: u128) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let cell = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(cell)
+    })
+}
+
+/// Propagate the pinned placements and print the simplified puzzle
+pub fn simplify(pins: &[usize]) -> Result<(), String> {
+    let mut domains = Domains::new();
+    domains.settle()?;
+    for id in pins {
+        domains.assign(*id)?;
+    }
+    let total = placements::entries().len();
+    println!(
+        "Placements left: {} of {}, assigned: {}, forced by propagation: {}",
+        domains.alive_count(),
+        total,
+        domains.assigned().len(),
+        domains.assigned().len() - pins.len()
+    );
+    let sizes: Vec<String> = (0..PIECES)
+        .map(|name| format!("{}:{}", i2c(name as isize), domains.size(name)))
+        .collect();
+    println!("Placements per piece: {}", sizes.join(" "));
+    let fewest = (0..CELLS)
+        .filter(|cell| domains.occupied & 1 << cell == 0)
+        .min_by_key(|cell| domains.covers(*cell));
+    if let Some(cell) = fewest {
+        println!(
+            "Empty cell with the fewest placements: {} with {}",
+            cell_name(cell),
+            domains.covers(cell)
+        );
+    }
+    println!("Assigned placements: {}", domains.printbox());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_and_undo() {
+        let mut domains = Domains::new();
+        let fresh = domains.clone();
+        domains.settle().unwrap();
+        let mark = domains.mark();
+        let id = placements::id(7, 4195).unwrap();
+        domains.assign(id).unwrap();
+        assert_eq!(domains.size(7), 1);
+        assert!(domains.assigned().contains(&id));
+        let mask = placements::entry(id).mask;
+        assert!((0..placements::entries().len())
+            .filter(|other| domains.is_alive(*other) && *other != id)
+            .all(|other| placements::entry(other).mask & mask == 0));
+        let other = placements::range(7).find(|other| *other != id).unwrap();
+        assert!(domains.assign(other).is_err());
+        domains.undo(mark);
+        assert_eq!(domains.alive, fresh.alive);
+        assert_eq!(domains.cells, fresh.cells);
+        assert_eq!(domains.assigned(), fresh.assigned());
+    }
+
+    #[test]
+    fn test_units_and_conflicts() {
+        let mut domains = Domains::new();
+        // a second placement of piece a fails, the piece is placed already
+        let mark = domains.mark();
+        let mut a = placements::range(0);
+        assert!(domains.assign(a.next().unwrap()).is_ok());
+        assert!(domains.assign(a.next().unwrap()).is_err());
+        domains.undo(mark);
+        // a piece with one placement left is placed
+        for id in placements::range(1).skip(1) {
+            domains.remove(id);
+        }
+        domains.settle().unwrap();
+        assert!(domains.assigned().contains(&placements::range(1).start));
+        assert_eq!(domains.size(1), 1);
+    }
+}
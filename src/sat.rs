@@ -0,0 +1,640 @@
+//! The packing problem as satisfiability problem in DIMACS CNF
+//!
+//! Every placement of a piece is a variable, true if the piece is placed
+//! there. Every cell is covered by exactly one placement and every piece
+//! has exactly one placement. The anchors of FIXED_MAP are encoded either
+//! by leaving out the placements off the anchor, or with variables for all
+//! placements in the box and a clause per anchored piece that one of its
+//! placements covers the anchor. At most one is encoded pairwise or with
+//! the sequential counter of Sinz, which needs auxiliary variables after
+//! the placement variables but grows linearly.
+//!
+//! The DIMACS file starts with comments that map the placement variables
+//! to `name:combination`, so a model can be turned into a box again.
+//!
+//! `solve` runs the solver of `cdcl` on the same clauses in process and
+//! enumerates packings by blocking the placements of every model found.
+//! The free pieces w, x and y have the same placements, so the model is
+//! blocked with all 6 orders of them and a packing is counted once, like
+//! the search of `solver` counts it.
+
+mod cdcl;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::cube::{cell, PrintBox, CELLS};
+use crate::piece::{anchor, is_free, PIECES};
+use crate::{c2i, i2c, placements};
+
+use self::cdcl::{Answer, Budget, Solver};
+
+/// Encoding of at most one of a set of variables
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtMostOne {
+    Pairwise,
+    Sequential,
+}
+
+impl FromStr for AtMostOne {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pairwise" => Ok(AtMostOne::Pairwise),
+            "sequential" => Ok(AtMostOne::Sequential),
+            _ => Err(format!("Unknown at most one encoding {}", s)),
+        }
+    }
+}
+
+/// Encoding of the anchors of FIXED_MAP
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchors {
+    /// only the placements on the anchor have a variable
+    Variables,
+    /// all placements in the box have a variable, a clause per anchor
+    Clauses,
+}
+
+impl FromStr for Anchors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "variables" => Ok(Anchors::Variables),
+            "clauses" => Ok(Anchors::Clauses),
+            _ => Err(format!("Unknown anchor encoding {}", s)),
+        }
+    }
+}
+
+/// A placement that has a variable
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub name: usize,
+    pub combination: u16,
+    pub mask: u128,
+}
+
+/// Clauses over the placement variables and auxiliary variables
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cnf {
+    /// placement of variable i + 1
+    pub placements: Vec<Placement>,
+    /// number of all variables, auxiliary ones included
+    pub variables: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+impl Cnf {
+    fn new(placements: Vec<Placement>) -> Self {
+        Cnf {
+            variables: placements.len(),
+            placements,
+            clauses: Vec::new(),
+        }
+    }
+
+    fn exactly_one(&mut self, literals: &[i32], at_most_one: AtMostOne) {
+        self.clauses.push(literals.to_vec());
+        match at_most_one {
+            AtMostOne::Pairwise => {
+                for (i, a) in literals.iter().enumerate() {
+                    for b in &literals[i + 1..] {
+                        self.clauses.push(vec![-a, -b]);
+                    }
+                }
+            }
+            AtMostOne::Sequential => {
+                // s_i: one of the first i + 1 literals is true
+                let n = literals.len();
+                if n < 2 {
+                    return;
+                }
+                let first = self.variables as i32 + 1;
+                self.variables += n - 1;
+                let s = |i: usize| first + i as i32;
+                self.clauses.push(vec![-literals[0], s(0)]);
+                for (i, literal) in literals.iter().enumerate().take(n - 1).skip(1) {
+                    self.clauses.push(vec![-literal, s(i)]);
+                    self.clauses.push(vec![-s(i - 1), s(i)]);
+                    self.clauses.push(vec![-literal, -s(i - 1)]);
+                }
+                self.clauses.push(vec![-literals[n - 1], -s(n - 2)]);
+            }
+        }
+    }
+
+    /// The placements of the true placement variables of a model
+    pub fn decode(&self, model: &[i32]) -> Vec<Placement> {
+        model
+            .iter()
+            .filter(|literal| **literal > 0 && **literal as usize <= self.placements.len())
+            .map(|literal| self.placements[*literal as usize - 1])
+            .collect()
+    }
+}
+
+/// The packing problem as CNF
+pub fn encode(at_most_one: AtMostOne, anchors: Anchors) -> Cnf {
+    let placements: Vec<Placement> = match anchors {
+        Anchors::Variables => placements::entries()
+            .iter()
+            .map(|entry| Placement {
+                name: entry.name,
+                combination: entry.combination,
+                mask: entry.mask,
+            })
+            .collect(),
+        // the free piece 'y' has all placements of the shape in the box
+        Anchors::Clauses => (0..PIECES)
+            .flat_map(|name| {
+                placements::entries()[placements::range(PIECES - 1)]
+                    .iter()
+                    .map(move |entry| Placement {
+                        name,
+                        combination: entry.combination,
+                        mask: entry.mask,
+                    })
+            })
+            .collect(),
+    };
+    let mut cnf = Cnf::new(placements);
+    let variables = |filter: &dyn Fn(&Placement) -> bool| -> Vec<i32> {
+        (1..)
+            .zip(cnf.placements.iter())
+            .filter(|(_, placement)| filter(placement))
+            .map(|(variable, _)| variable)
+            .collect()
+    };
+    let cells: Vec<Vec<i32>> = (0..CELLS)
+        .map(|cell| variables(&|p| p.mask & 1 << cell != 0))
+        .collect();
+    let pieces: Vec<Vec<i32>> = (0..PIECES)
+        .map(|name| variables(&|p| p.name == name))
+        .collect();
+    let anchored: Vec<Vec<i32>> = match anchors {
+        Anchors::Variables => Vec::new(),
+        Anchors::Clauses => (0..PIECES)
+            .filter_map(|name| {
+                let anchor = cell(anchor(name)?);
+                Some(variables(&|p| p.name == name && p.mask & 1 << anchor != 0))
+            })
+            .collect(),
+    };
+    for literals in cells.iter().chain(pieces.iter()) {
+        cnf.exactly_one(literals, at_most_one);
+    }
+    cnf.clauses.extend(anchored);
+    cnf
+}
+
+/// The CNF as DIMACS text with the placements of the variables as comments
+pub fn dimacs(cnf: &Cnf) -> String {
+    let mut text = String::new();
+    writeln!(text, "c p3d packing of the 5x5x5 box").unwrap();
+    for (variable, placement) in (1..).zip(cnf.placements.iter()) {
+        writeln!(
+            text,
+            "c placement {} {}:{}",
+            variable,
+            i2c(placement.name as isize),
+            placement.combination
+        )
+        .unwrap();
+    }
+    writeln!(text, "p cnf {} {}", cnf.variables, cnf.clauses.len()).unwrap();
+    for clause in &cnf.clauses {
+        for literal in clause {
+            write!(text, "{} ", literal).unwrap();
+        }
+        writeln!(text, "0").unwrap();
+    }
+    text
+}
+
+/// The placement variables from the comments of a DIMACS file
+pub fn parse_placements(content: &str) -> Result<HashMap<i32, Placement>, String> {
+    let mut placements = HashMap::new();
+    for line in content.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("c") || words.next() != Some("placement") {
+            continue;
+        }
+        let (variable, placement) = match (words.next(), words.next()) {
+            (Some(variable), Some(placement)) => (variable, placement),
+            _ => return Err(format!("{} is no placement comment", line)),
+        };
+        let variable = variable
+            .parse::<i32>()
+            .map_err(|_| format!("{} is no variable", variable))?;
+        let (name, combination) = placement
+            .split_once(':')
+            .ok_or_else(|| format!("{} is no name:combination", placement))?;
+        let name = name
+            .chars()
+            .next()
+            .and_then(c2i)
+            .filter(|n| (0..PIECES as isize).contains(n))
+            .ok_or_else(|| format!("{} is no piece name", name))? as usize;
+        let combination = combination
+            .parse::<u16>()
+            .map_err(|_| format!("{} is no combination", combination))?;
+        // the mask of the shape does not depend on the piece
+        let shapes = placements::range(PIECES - 1);
+        let mask = placements::entries()[shapes]
+            .iter()
+            .find(|entry| entry.combination == combination)
+            .ok_or_else(|| format!("{} is no placement in the box", placement))?
+            .mask;
+        placements.insert(
+            variable,
+            Placement {
+                name,
+                combination,
+                mask,
+            },
+        );
+    }
+    Ok(placements)
+}
+
+/// The literals of a model in the output format of common SAT solvers
+///
+/// Accepts `v` lines of the competition format as well as plain literals
+/// after a `SAT` line like minisat writes them.
+pub fn parse_model(content: &str) -> Result<Vec<i32>, String> {
+    let mut literals = Vec::new();
+    for line in content.lines().map(str::trim) {
+        match line.split_whitespace().next() {
+            None | Some("c") | Some("SAT") => {}
+            Some("s") | Some("UNSAT") => {
+                if line.contains("UNSAT") {
+                    return Err("the problem is unsatisfiable".to_string());
+                }
+            }
+            Some(_) => {
+                for word in line.split_whitespace().filter(|w| *w != "v") {
+                    let literal = word
+                        .parse::<i32>()
+                        .map_err(|_| format!("{} is no literal", word))?;
+                    if literal != 0 {
+                        literals.push(literal);
+                    }
+                }
+            }
+        }
+    }
+    Ok(literals)
+}
+
+/// The box of the true placements and the complaints if it is no packing
+pub fn reconstruct(placements: &[Placement]) -> (PrintBox, Vec<String>) {
+    let mut b = PrintBox::new();
+    let mut complaints = Vec::new();
+    let mut occupied: u128 = 0;
+    let mut count = [0usize; PIECES];
+    for placement in placements {
+        count[placement.name] += 1;
+        if occupied & placement.mask != 0 {
+            complaints.push(format!(
+                "{}:{} overlaps",
+                i2c(placement.name as isize),
+                placement.combination
+            ));
+        }
+        occupied |= placement.mask;
+        b.add_mask(placement.mask, placement.name);
+    }
+    for (name, count) in count.iter().enumerate() {
+        if *count != 1 {
+            complaints.push(format!(
+                "piece {} is placed {} times",
+                i2c(name as isize),
+                count
+            ));
+        }
+    }
+    let empty = CELLS - occupied.count_ones() as usize;
+    if empty > 0 {
+        complaints.push(format!("{} cells are empty", empty));
+    }
+    (b, complaints)
+}
+
+/// Write the DIMACS file or print it
+pub fn export(file: Option<&str>, at_most_one: AtMostOne, anchors: Anchors) -> Result<(), String> {
+    let cnf = encode(at_most_one, anchors);
+    let text = dimacs(&cnf);
+    match file {
+        None => print!("{}", text),
+        Some(file) => {
+            fs::write(file, text).map_err(|e| format!("{}: {}", file, e))?;
+            println!(
+                "CNF with {} variables, {} of them placements, and {} clauses saved to {}",
+                cnf.variables,
+                cnf.placements.len(),
+                cnf.clauses.len(),
+                file
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the box of the model of a SAT solver for an exported DIMACS file
+pub fn import(cnf_file: &str, model_file: &str) -> Result<(), String> {
+    let cnf = fs::read_to_string(cnf_file).map_err(|e| format!("{}: {}", cnf_file, e))?;
+    let variables = parse_placements(&cnf).map_err(|e| format!("{}: {}", cnf_file, e))?;
+    let model = fs::read_to_string(model_file).map_err(|e| format!("{}: {}", model_file, e))?;
+    let model = parse_model(&model).map_err(|e| format!("{}: {}", model_file, e))?;
+    let placements: Vec<Placement> = model
+        .iter()
+        .filter_map(|literal| variables.get(literal))
+        .copied()
+        .collect();
+    let (b, complaints) = reconstruct(&placements);
+    if complaints.is_empty() {
+        println!("Solution: {}", b);
+    } else {
+        println!("No packing, {}: {}", complaints.join(", "), b);
+    }
+    Ok(())
+}
+
+/// Settings of the embedded SAT solver
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub at_most_one: AtMostOne,
+    pub anchors: Anchors,
+    /// stop after this many packings, 0 for all
+    pub solutions: usize,
+    /// conflicts of the whole run, over all packings
+    pub conflict_limit: Option<u64>,
+    /// seconds
+    pub time_limit: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            at_most_one: AtMostOne::Sequential,
+            anchors: Anchors::Variables,
+            solutions: 1,
+            conflict_limit: None,
+            time_limit: None,
+        }
+    }
+}
+
+/// All orders of the names
+fn orders(names: &[usize]) -> Vec<Vec<usize>> {
+    if names.is_empty() {
+        return vec![Vec::new()];
+    }
+    (0..names.len())
+        .flat_map(|i| {
+            let mut rest = names.to_vec();
+            let first = rest.remove(i);
+            orders(&rest).into_iter().map(move |mut order| {
+                order.insert(0, first);
+                order
+            })
+        })
+        .collect()
+}
+
+/// Clauses that block the chosen placements with every order of the free pieces
+fn blocking_clauses(
+    cnf: &Cnf,
+    variables: &HashMap<(usize, u16), i32>,
+    chosen: &[i32],
+) -> Vec<Vec<i32>> {
+    let free: Vec<usize> = (0..PIECES).filter(|name| is_free(*name)).collect();
+    let mut clauses: Vec<Vec<i32>> = orders(&free)
+        .iter()
+        .filter_map(|order| {
+            chosen
+                .iter()
+                .map(|variable| {
+                    let placement = cnf.placements[*variable as usize - 1];
+                    let name = free
+                        .iter()
+                        .position(|name| *name == placement.name)
+                        .map_or(placement.name, |i| order[i]);
+                    variables.get(&(name, placement.combination)).map(|v| -v)
+                })
+                .collect::<Option<Vec<i32>>>()
+        })
+        .collect();
+    for clause in &mut clauses {
+        clause.sort_unstable();
+    }
+    clauses.sort();
+    clauses.dedup();
+    clauses
+}
+
+/// Models of the CNF that differ in the placements, handed to `found`
+///
+/// Returns why the enumeration stopped, `None` if no other model exists.
+fn enumerate<F>(cnf: &Cnf, settings: &Settings, solver: &mut Solver, mut found: F) -> Option<String>
+where
+    F: FnMut(Vec<Placement>),
+{
+    let budget = Budget {
+        conflicts: settings.conflict_limit,
+        time: settings
+            .time_limit
+            .map(|seconds| (Instant::now(), Duration::from_secs(seconds))),
+    };
+    let variables: HashMap<(usize, u16), i32> = (1..)
+        .zip(cnf.placements.iter())
+        .map(|(variable, p)| ((p.name, p.combination), variable))
+        .collect();
+    let mut solutions = 0;
+    loop {
+        match solver.solve(&budget) {
+            Answer::Unsatisfiable => return None,
+            Answer::Unknown(reason) => return Some(reason),
+            Answer::Satisfiable(model) => {
+                let chosen: Vec<i32> = (1..=cnf.placements.len() as i32)
+                    .filter(|v| model[*v as usize - 1])
+                    .collect();
+                for clause in blocking_clauses(cnf, &variables, &chosen) {
+                    solver.add_clause(&clause);
+                }
+                found(cnf.decode(&chosen));
+                solutions += 1;
+                if solutions == settings.solutions {
+                    return Some(format!("{} solutions found", solutions));
+                }
+            }
+        }
+    }
+}
+
+/// Search packings with the embedded SAT solver and print them with the statistics
+pub fn solve(settings: &Settings) {
+    let started = Instant::now();
+    let cnf = encode(settings.at_most_one, settings.anchors);
+    let mut solver = Solver::new(cnf.variables);
+    for clause in &cnf.clauses {
+        solver.add_clause(clause);
+    }
+    println!(
+        "CNF with {} variables, {} of them placements, and {} clauses",
+        cnf.variables,
+        cnf.placements.len(),
+        cnf.clauses.len()
+    );
+    let mut solutions = 0;
+    let stop_reason = enumerate(&cnf, settings, &mut solver, |placements| {
+        solutions += 1;
+        let (b, complaints) = reconstruct(&placements);
+        if complaints.is_empty() {
+            println!("Solution #{}: {}", solutions, b);
+        } else {
+            println!("No packing, {}: {}", complaints.join(", "), b);
+        }
+    })
+    .unwrap_or_else(|| "unsatisfiable, no other packing exists".to_string());
+    println!("Stop criterion: {}", stop_reason);
+    let stats = &solver.stats;
+    println!(
+        "SAT: decisions: {}, propagations: {}, conflicts: {}, restarts: {}, learnt clauses: {}, \
+         solutions: {}, runtime: {:.3}s",
+        stats.decisions,
+        stats.propagations,
+        stats.conflicts,
+        stats.restarts,
+        stats.learnt,
+        solutions,
+        started.elapsed().as_secs_f64()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfied(cnf: &Cnf, model: &[bool]) -> bool {
+        cnf.clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|l| model[l.unsigned_abs() as usize - 1] == (*l > 0))
+        })
+    }
+
+    #[test]
+    fn test_exactly_one() {
+        for at_most_one in [AtMostOne::Pairwise, AtMostOne::Sequential] {
+            let mut cnf = Cnf::new(Vec::new());
+            cnf.variables = 4;
+            cnf.exactly_one(&[1, 2, 3, 4], at_most_one);
+            // some assignment of the auxiliary variables satisfies the clauses
+            let count = |x: &[bool]| {
+                (0..1 << (cnf.variables - 4)).any(|aux: usize| {
+                    let mut model = x.to_vec();
+                    model.extend((0..cnf.variables - 4).map(|i| aux & 1 << i != 0));
+                    satisfied(&cnf, &model)
+                })
+            };
+            assert!(count(&[false, true, false, false]));
+            assert!(!count(&[false, false, false, false]));
+            assert!(!count(&[true, false, true, false]));
+        }
+    }
+
+    #[test]
+    fn test_dimacs_round_trip() {
+        let cnf = encode(AtMostOne::Sequential, Anchors::Variables);
+        assert_eq!(cnf.placements.len(), placements::entries().len());
+        let text = dimacs(&cnf);
+        assert!(text.contains(&format!("p cnf {} {}", cnf.variables, cnf.clauses.len())));
+        let mapping = parse_placements(&text).unwrap();
+        assert_eq!(mapping[&1], cnf.placements[0]);
+        let model = parse_model("SAT\n1 -2 3 0\n").unwrap();
+        assert_eq!(
+            cnf.decode(&model),
+            vec![cnf.placements[0], cnf.placements[2]]
+        );
+        assert_eq!(
+            parse_model("s SATISFIABLE\nv -1 2\nv 0").unwrap(),
+            vec![-1, 2]
+        );
+        assert!(parse_model("s UNSATISFIABLE").is_err());
+        let (_, complaints) = reconstruct(&cnf.decode(&model));
+        assert!(!complaints.is_empty());
+    }
+
+    #[test]
+    fn test_enumerate_blocks_placements() {
+        let placements = (0..3)
+            .map(|combination| Placement {
+                name: 0,
+                combination,
+                mask: 1 << combination,
+            })
+            .collect();
+        let mut cnf = Cnf::new(placements);
+        cnf.exactly_one(&[1, 2, 3], AtMostOne::Sequential);
+        let enumerate_all = |solutions| {
+            let mut solver = Solver::new(cnf.variables);
+            for clause in &cnf.clauses {
+                solver.add_clause(clause);
+            }
+            let settings = Settings {
+                solutions,
+                ..Settings::default()
+            };
+            let mut found = Vec::new();
+            let stop = enumerate(&cnf, &settings, &mut solver, |p| found.push(p));
+            found.sort_by_key(|p: &Vec<Placement>| p[0].combination);
+            (found, stop)
+        };
+        let (found, stop) = enumerate_all(0);
+        assert_eq!(stop, None);
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().all(|p| p.len() == 1));
+        assert_eq!(enumerate_all(2).0.len(), 2);
+    }
+
+    #[test]
+    fn test_enumerate_counts_free_pieces_once() {
+        // w, x and y in three cells: 6 models, all the same packing
+        let free: Vec<usize> = (0..PIECES).filter(|name| is_free(*name)).collect();
+        let placements = free
+            .iter()
+            .flat_map(|name| {
+                (0..3).map(move |combination| Placement {
+                    name: *name,
+                    combination,
+                    mask: 1 << combination,
+                })
+            })
+            .collect();
+        let mut cnf = Cnf::new(placements);
+        for i in 0..3 {
+            cnf.exactly_one(&[3 * i + 1, 3 * i + 2, 3 * i + 3], AtMostOne::Pairwise);
+            cnf.exactly_one(&[i + 1, i + 4, i + 7], AtMostOne::Pairwise);
+        }
+        assert_eq!(orders(&free).len(), 6);
+        let mut solver = Solver::new(cnf.variables);
+        for clause in &cnf.clauses {
+            solver.add_clause(clause);
+        }
+        let settings = Settings {
+            solutions: 0,
+            ..Settings::default()
+        };
+        let mut found = Vec::new();
+        let stop = enumerate(&cnf, &settings, &mut solver, |p| found.push(p));
+        assert_eq!(stop, None);
+        assert_eq!(found.len(), 1);
+    }
+}
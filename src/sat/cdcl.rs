@@ -0,0 +1,625 @@
+//! A small conflict driven clause learning SAT solver
+//!
+//! Two watched literals per clause for unit propagation, first UIP
+//! learning, variable activities with decay for the decisions, saved
+//! phases, restarts after Luby many conflicts and the reduction of the
+//! learnt clauses by literal block distance on restart. Clauses can be
+//! added between two calls of `solve`, which is how solutions are blocked.
+//!
+//! p3d builds without network access to crates.io, so it carries its own
+//! solver instead of depending on varisat or splr. The tests check it
+//! against brute force on random formulas.
+
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+/// Variable 2 v is positive, 2 v + 1 negative
+type Lit = usize;
+
+fn var(lit: Lit) -> usize {
+    lit >> 1
+}
+
+fn neg(lit: Lit) -> Lit {
+    lit ^ 1
+}
+
+/// The literal of a DIMACS literal, variables count from 1
+fn from_dimacs(literal: i32) -> Lit {
+    let var = literal.unsigned_abs() as usize - 1;
+    2 * var + usize::from(literal < 0)
+}
+
+const UNASSIGNED: i8 = 0;
+/// Conflicts of the first restart, multiplied by the Luby sequence
+const RESTART_BASE: u64 = 100;
+const ACTIVITY_DECAY: f64 = 0.95;
+/// Learnt clauses of at most this distance are kept on reduction
+const GLUE: usize = 2;
+
+#[derive(Clone, Debug)]
+struct Clause {
+    literals: Vec<Lit>,
+    learnt: bool,
+    /// number of decision levels of the literals on learning
+    lbd: usize,
+}
+
+/// Conflicts or decisions between two checks of the time limit
+const TIME_CHECK: u64 = 256;
+
+/// Conflict and time budget of the solver over all calls of `solve`
+///
+/// An enumeration calls `solve` once per model, the budget is that of the
+/// whole enumeration.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    /// conflicts of the solver since its creation
+    pub conflicts: Option<u64>,
+    /// start of the time and the time limit
+    pub time: Option<(Instant, Duration)>,
+}
+
+/// Counters of the solver over all calls
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub decisions: u64,
+    pub propagations: u64,
+    pub conflicts: u64,
+    pub restarts: u64,
+    pub learnt: u64,
+}
+
+/// The answer of a call of `solve`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Answer {
+    /// the value of every variable
+    Satisfiable(Vec<bool>),
+    Unsatisfiable,
+    /// a limit stopped the search
+    Unknown(String),
+}
+
+pub struct Solver {
+    clauses: Vec<Clause>,
+    /// per literal the clauses that watch it
+    watches: Vec<Vec<usize>>,
+    /// per variable 1 true, -1 false or UNASSIGNED
+    values: Vec<i8>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    /// trail length at the start of every decision level
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    increment: f64,
+    /// unassigned variables by activity, may contain outdated entries
+    order: BinaryHeap<(u64, usize)>,
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+    max_learnt: usize,
+    /// false once the clauses are known to be unsatisfiable
+    consistent: bool,
+    pub stats: Stats,
+}
+
+impl Solver {
+    pub fn new(variables: usize) -> Self {
+        Solver {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * variables],
+            values: vec![UNASSIGNED; variables],
+            levels: vec![0; variables],
+            reasons: vec![None; variables],
+            trail: Vec::with_capacity(variables),
+            trail_limits: Vec::new(),
+            propagated: 0,
+            activity: vec![0.0; variables],
+            increment: 1.0,
+            order: (0..variables).map(|v| (0f64.to_bits(), v)).collect(),
+            phases: vec![false; variables],
+            seen: vec![false; variables],
+            max_learnt: 10000,
+            consistent: true,
+            stats: Stats::default(),
+        }
+    }
+
+    fn value(&self, lit: Lit) -> i8 {
+        let value = self.values[var(lit)];
+        if lit & 1 == 1 {
+            -value
+        } else {
+            value
+        }
+    }
+
+    fn level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let v = var(lit);
+        self.values[v] = if lit & 1 == 1 { -1 } else { 1 };
+        self.levels[v] = self.level();
+        self.reasons[v] = reason;
+        self.trail.push(lit);
+    }
+
+    fn attach(&mut self, index: usize) {
+        let literals = &self.clauses[index].literals;
+        self.watches[literals[0]].push(index);
+        self.watches[literals[1]].push(index);
+    }
+
+    /// Add a clause of DIMACS literals, only between two calls of `solve`
+    pub fn add_clause(&mut self, clause: &[i32]) {
+        self.cancel(0);
+        let mut literals: Vec<Lit> = Vec::with_capacity(clause.len());
+        for literal in clause.iter().map(|l| from_dimacs(*l)) {
+            if self.value(literal) == 1 || literals.contains(&neg(literal)) {
+                return;
+            }
+            if self.value(literal) == UNASSIGNED && !literals.contains(&literal) {
+                literals.push(literal);
+            }
+        }
+        match literals.len() {
+            0 => self.consistent = false,
+            1 => {
+                self.assign(literals[0], None);
+                if self.propagate().is_some() {
+                    self.consistent = false;
+                }
+            }
+            _ => {
+                self.clauses.push(Clause {
+                    literals,
+                    learnt: false,
+                    lbd: 0,
+                });
+                self.attach(self.clauses.len() - 1);
+            }
+        }
+    }
+
+    /// Unit propagation, the index of a conflicting clause if there is one
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_literal = neg(self.trail[self.propagated]);
+            self.propagated += 1;
+            self.stats.propagations += 1;
+            let mut watching = std::mem::take(&mut self.watches[false_literal]);
+            let mut kept = 0;
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watching.len() {
+                let index = watching[i];
+                i += 1;
+                let literals = &mut self.clauses[index].literals;
+                if literals[0] == false_literal {
+                    literals.swap(0, 1);
+                }
+                let first = literals[0];
+                if self.value(first) == 1 {
+                    watching[kept] = index;
+                    kept += 1;
+                    continue;
+                }
+                let literals = &self.clauses[index].literals;
+                let replacement = (2..literals.len()).find(|k| self.value(literals[*k]) != -1);
+                if let Some(k) = replacement {
+                    let literals = &mut self.clauses[index].literals;
+                    literals.swap(1, k);
+                    let watched = literals[1];
+                    self.watches[watched].push(index);
+                    continue;
+                }
+                watching[kept] = index;
+                kept += 1;
+                if self.value(first) == -1 {
+                    conflict = Some(index);
+                    while i < watching.len() {
+                        watching[kept] = watching[i];
+                        kept += 1;
+                        i += 1;
+                    }
+                } else {
+                    self.assign(first, Some(index));
+                }
+            }
+            watching.truncate(kept);
+            self.watches[false_literal] = watching;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.increment;
+        if self.activity[v] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.increment *= 1e-100;
+            self.rebuild_order();
+        } else if self.values[v] == UNASSIGNED {
+            self.order.push((self.activity[v].to_bits(), v));
+        }
+    }
+
+    fn rebuild_order(&mut self) {
+        self.order = (0..self.values.len())
+            .filter(|v| self.values[*v] == UNASSIGNED)
+            .map(|v| (self.activity[v].to_bits(), v))
+            .collect();
+    }
+
+    /// First UIP clause of a conflict and the level to go back to
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![0];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut implied: Option<Lit> = None;
+        loop {
+            let skip = usize::from(implied.is_some());
+            for k in skip..self.clauses[conflict].literals.len() {
+                let literal = self.clauses[conflict].literals[k];
+                let v = var(literal);
+                if self.seen[v] || self.levels[v] == 0 {
+                    continue;
+                }
+                self.seen[v] = true;
+                self.bump(v);
+                if self.levels[v] == self.level() {
+                    pending += 1;
+                } else {
+                    learnt.push(literal);
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[var(self.trail[index])] {
+                    break;
+                }
+            }
+            let literal = self.trail[index];
+            self.seen[var(literal)] = false;
+            pending -= 1;
+            if pending == 0 {
+                implied = Some(literal);
+                break;
+            }
+            implied = Some(literal);
+            conflict = self.reasons[var(literal)].unwrap();
+        }
+        learnt[0] = neg(implied.unwrap());
+        for literal in &learnt[1..] {
+            self.seen[var(*literal)] = false;
+        }
+        let mut back = 0;
+        if learnt.len() > 1 {
+            let highest = (1..learnt.len())
+                .max_by_key(|k| self.levels[var(learnt[*k])])
+                .unwrap();
+            learnt.swap(1, highest);
+            back = self.levels[var(learnt[1])];
+        }
+        (learnt, back)
+    }
+
+    /// Undo all assignments above the level
+    fn cancel(&mut self, level: usize) {
+        if self.level() <= level {
+            return;
+        }
+        let start = self.trail_limits[level];
+        for k in (start..self.trail.len()).rev() {
+            let v = var(self.trail[k]);
+            self.phases[v] = self.values[v] == 1;
+            self.values[v] = UNASSIGNED;
+            self.reasons[v] = None;
+            self.order.push((self.activity[v].to_bits(), v));
+        }
+        self.trail.truncate(start);
+        self.trail_limits.truncate(level);
+        self.propagated = start;
+    }
+
+    /// The unassigned variable with the highest activity
+    fn pick(&mut self) -> Option<usize> {
+        if self.order.len() > 4 * self.values.len() + 100 {
+            self.rebuild_order();
+        }
+        while let Some((bits, v)) = self.order.pop() {
+            if self.values[v] == UNASSIGNED && bits == self.activity[v].to_bits() {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    /// Keep the learnt clauses of low distance and the better half of the others
+    fn reduce(&mut self) {
+        let learnt = self.clauses.iter().filter(|c| c.learnt).count();
+        if learnt <= self.max_learnt {
+            return;
+        }
+        let mut distances: Vec<usize> = self
+            .clauses
+            .iter()
+            .filter(|c| c.learnt && c.lbd > GLUE)
+            .map(|c| c.lbd)
+            .collect();
+        distances.sort_unstable();
+        let limit = distances.get(distances.len() / 2).copied().unwrap_or(0);
+        // on level 0 no reason is needed for an analysis any more
+        for reason in self.reasons.iter_mut() {
+            *reason = None;
+        }
+        self.clauses
+            .retain(|c| !c.learnt || c.lbd <= GLUE || c.lbd < limit);
+        for watching in self.watches.iter_mut() {
+            watching.clear();
+        }
+        for index in 0..self.clauses.len() {
+            self.attach(index);
+        }
+        self.max_learnt += self.max_learnt / 10;
+    }
+
+    /// `Unknown` after the time limit, the search is cancelled then
+    fn out_of_time(&mut self, budget: &Budget) -> Option<Answer> {
+        let (started, limit) = budget.time?;
+        if started.elapsed() < limit {
+            return None;
+        }
+        self.cancel(0);
+        Some(Answer::Unknown(format!(
+            "time limit of {:?} reached",
+            limit
+        )))
+    }
+
+    /// Search a model of all clauses within the budget
+    pub fn solve(&mut self, budget: &Budget) -> Answer {
+        if !self.consistent {
+            return Answer::Unsatisfiable;
+        }
+        let mut restart = 1;
+        let mut until_restart = RESTART_BASE * luby(restart);
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.stats.conflicts += 1;
+                if self.level() == 0 {
+                    self.consistent = false;
+                    return Answer::Unsatisfiable;
+                }
+                let (learnt, back) = self.analyze(conflict);
+                self.cancel(back);
+                self.stats.learnt += 1;
+                self.increment /= ACTIVITY_DECAY;
+                if learnt.len() == 1 {
+                    self.assign(learnt[0], None);
+                } else {
+                    let mut levels: Vec<usize> =
+                        learnt.iter().map(|l| self.levels[var(*l)]).collect();
+                    levels.sort_unstable();
+                    levels.dedup();
+                    let first = learnt[0];
+                    self.clauses.push(Clause {
+                        literals: learnt,
+                        learnt: true,
+                        lbd: levels.len() + 1,
+                    });
+                    let index = self.clauses.len() - 1;
+                    self.attach(index);
+                    self.assign(first, Some(index));
+                }
+                until_restart = until_restart.saturating_sub(1);
+                if let Some(limit) = budget.conflicts {
+                    if self.stats.conflicts >= limit {
+                        self.cancel(0);
+                        return Answer::Unknown(format!("conflict limit of {} reached", limit));
+                    }
+                }
+                if self.stats.conflicts.is_multiple_of(TIME_CHECK) {
+                    if let Some(answer) = self.out_of_time(budget) {
+                        return answer;
+                    }
+                }
+            } else if until_restart == 0 {
+                self.stats.restarts += 1;
+                restart += 1;
+                until_restart = RESTART_BASE * luby(restart);
+                self.cancel(0);
+                self.reduce();
+            } else {
+                match self.pick() {
+                    None => {
+                        let model = self.values.iter().map(|v| *v == 1).collect();
+                        self.cancel(0);
+                        return Answer::Satisfiable(model);
+                    }
+                    Some(v) => {
+                        self.stats.decisions += 1;
+                        // a search with few conflicts still stops in time
+                        if self.stats.decisions.is_multiple_of(TIME_CHECK) {
+                            if let Some(answer) = self.out_of_time(budget) {
+                                return answer;
+                            }
+                        }
+                        self.trail_limits.push(self.trail.len());
+                        let literal = 2 * v + usize::from(!self.phases[v]);
+                        self.assign(literal, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, ... from index 1
+fn luby(mut i: u64) -> u64 {
+    loop {
+        let mut k = 1;
+        while (1 << k) - 1 < i {
+            k += 1;
+        }
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genevo::random::{random_index, Prng, Rng, SeedableRng};
+
+    /// n + 1 pigeons in n holes, variable p * n + h + 1: pigeon p in hole h
+    fn pigeons(n: usize) -> Solver {
+        let var = |p: usize, h: usize| (p * n + h + 1) as i32;
+        let mut solver = Solver::new((n + 1) * n);
+        for p in 0..=n {
+            solver.add_clause(&(0..n).map(|h| var(p, h)).collect::<Vec<_>>());
+        }
+        for h in 0..n {
+            for p in 0..=n {
+                for q in p + 1..=n {
+                    solver.add_clause(&[-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        solver
+    }
+
+    #[test]
+    fn test_luby() {
+        let sequence: Vec<u64> = (1..=8).map(luby).collect();
+        assert_eq!(sequence, vec![1, 1, 2, 1, 1, 2, 4, 1]);
+    }
+
+    #[test]
+    fn test_unsatisfiable() {
+        assert_eq!(pigeons(5).solve(&Budget::default()), Answer::Unsatisfiable);
+        let mut solver = Solver::new(1);
+        solver.add_clause(&[1]);
+        solver.add_clause(&[-1]);
+        assert_eq!(solver.solve(&Budget::default()), Answer::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_enumerate_with_blocking_clauses() {
+        // exactly one of three variables
+        let mut solver = Solver::new(3);
+        solver.add_clause(&[1, 2, 3]);
+        solver.add_clause(&[-1, -2]);
+        solver.add_clause(&[-1, -3]);
+        solver.add_clause(&[-2, -3]);
+        let mut models = Vec::new();
+        while let Answer::Satisfiable(model) = solver.solve(&Budget::default()) {
+            let true_variable = (1..=3).find(|v| model[*v as usize - 1]).unwrap();
+            models.push(true_variable);
+            solver.add_clause(&[-true_variable]);
+        }
+        models.sort_unstable();
+        assert_eq!(models, vec![1, 2, 3]);
+    }
+
+    /// Random clauses of 2 or 3 literals over `variables` variables
+    fn random_cnf(rng: &mut Prng, variables: usize, clauses: usize) -> Vec<Vec<i32>> {
+        (0..clauses)
+            .map(|_| {
+                (0..2 + random_index(rng, 2))
+                    .map(|_| {
+                        let variable = random_index(rng, variables) as i32 + 1;
+                        if rng.gen::<bool>() {
+                            variable
+                        } else {
+                            -variable
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn satisfies(clauses: &[Vec<i32>], model: &[bool]) -> bool {
+        clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|l| model[l.unsigned_abs() as usize - 1] == (*l > 0))
+        })
+    }
+
+    #[test]
+    fn test_random_cnf_against_brute_force() {
+        let mut rng = Prng::seed_from_u64(46);
+        for round in 0..300 {
+            let variables = 3 + round % 8;
+            let clauses = random_cnf(&mut rng, variables, variables + round % (4 * variables));
+            let models = (0..1usize << variables)
+                .map(|bits| {
+                    (0..variables)
+                        .map(|v| bits & 1 << v != 0)
+                        .collect::<Vec<_>>()
+                })
+                .filter(|model| satisfies(&clauses, model))
+                .count();
+            let mut solver = Solver::new(variables);
+            for clause in &clauses {
+                solver.add_clause(clause);
+            }
+            // every model is blocked after it is found, so each is found once
+            let mut found = 0;
+            loop {
+                match solver.solve(&Budget::default()) {
+                    Answer::Satisfiable(model) => {
+                        assert!(satisfies(&clauses, &model), "round {}", round);
+                        found += 1;
+                        let blocking: Vec<i32> = (1..=variables as i32)
+                            .map(|v| if model[v as usize - 1] { -v } else { v })
+                            .collect();
+                        solver.add_clause(&blocking);
+                    }
+                    Answer::Unsatisfiable => break,
+                    Answer::Unknown(reason) => panic!("round {}: {}", round, reason),
+                }
+            }
+            assert_eq!(found, models, "round {}: {:?}", round, clauses);
+        }
+    }
+
+    #[test]
+    fn test_budget() {
+        let budget = Budget {
+            conflicts: Some(1),
+            time: None,
+        };
+        assert!(matches!(pigeons(5).solve(&budget), Answer::Unknown(_)));
+        let budget = Budget {
+            conflicts: None,
+            time: Some((Instant::now(), Duration::from_secs(0))),
+        };
+        assert!(matches!(pigeons(7).solve(&budget), Answer::Unknown(_)));
+    }
+
+    #[test]
+    fn test_conflicts_over_all_calls() {
+        let budget = Budget {
+            conflicts: Some(2),
+            time: None,
+        };
+        let mut solver = pigeons(5);
+        assert!(matches!(solver.solve(&budget), Answer::Unknown(_)));
+        assert_eq!(solver.stats.conflicts, 2);
+        // a spent budget stops the next call at its first conflict
+        assert!(matches!(solver.solve(&budget), Answer::Unknown(_)));
+        assert_eq!(solver.stats.conflicts, 3);
+    }
+}
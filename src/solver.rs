@@ -0,0 +1,583 @@
+//! Deterministic depth-first search for complete packings
+//!
+//! The backtracker fills the box cell by cell: it picks the empty cell
+//! that the fewest placements of the unused pieces cover, tries each of
+//! these placements and removes the piece again when the subtree is done.
+//! The anchors of FIXED_MAP are part of the placements table, so anchored
+//! pieces only go where they may. The free pieces w, x and y have the same
+//! placements, they are used in the order of their names to not search
+//! the same packing with swapped names. So every packing is found and
+//! counted once, not once for each of the 6 relabelings of w, x and y,
+//! also by the threads and the work units. With pruning a node is rejected
+//! right away if its empty cells have a dead region, see `prune`.
+//!
+//! The optional transposition table skips partial packings that were
+//! searched before without a solution, see `transposition`.
+//!
+//! With propagation the search keeps the domains of `propagate` along:
+//! only placements that are still possible are candidates and a node
+//! whose propagation runs into a conflict is rejected.
+//!
+//! With threads the tree is split at a fixed depth into subtrees, the
+//! work units, that a thread pool searches in parallel, see `parallel`.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+mod estimate;
+mod parallel;
+pub mod prune;
+mod transposition;
+mod units;
+
+use crate::cube::{AddToBox, PrintBox, CELLS};
+use crate::limits::Limits;
+use crate::piece::{is_free, PIECES};
+use crate::placements;
+use crate::propagate::Domains;
+
+use self::transposition::TranspositionTable;
+
+/// All cells of the box as bit set
+const FULL: u128 = (1 << CELLS) - 1;
+/// Nodes between two checks of the time limit
+const CHECK_INTERVAL: u64 = 1 << 14;
+/// Nodes between two progress lines
+const REPORT_INTERVAL: u64 = 1 << 22;
+
+/// Limits of a search
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// stop after this number of packings, w, x and y in name order, 0 searches all
+    pub solutions: usize,
+    /// node and time limit
+    pub limits: Limits,
+    /// reject partial packings with empty regions that cannot be filled
+    pub prune: bool,
+    /// search the work units on this number of threads, one search if not set
+    pub threads: Option<usize>,
+    /// number of placed pieces of a work unit
+    pub split_depth: usize,
+    /// memory of the transposition table in MB, 0 turns it off
+    pub transposition_memory: usize,
+    /// narrow the candidates by constraint propagation
+    pub propagate: bool,
+    /// keep the placement ids of the packings, they are only printed otherwise
+    pub collect: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            solutions: 1,
+            limits: Limits::default(),
+            prune: true,
+            threads: None,
+            split_depth: 3,
+            transposition_memory: 0,
+            propagate: false,
+            collect: false,
+        }
+    }
+}
+
+/// Counters of a search
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// visited nodes, the root included
+    pub nodes: u64,
+    /// most pieces placed at the same time
+    pub max_depth: usize,
+    /// nodes with an empty cell that no placement fits
+    pub dead_ends: u64,
+    /// nodes rejected because of a dead region
+    pub pruned: u64,
+    /// nodes skipped because the transposition table knows them
+    pub transpositions: u64,
+    /// nodes rejected because the propagation ran into a conflict
+    pub conflicts: u64,
+    pub solutions: usize,
+}
+
+impl Stats {
+    /// Add the counters of another search
+    fn merge(&mut self, other: &Stats) {
+        self.nodes += other.nodes;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.dead_ends += other.dead_ends;
+        self.pruned += other.pruned;
+        self.transpositions += other.transpositions;
+        self.conflicts += other.conflicts;
+        self.solutions += other.solutions;
+    }
+}
+
+/// State that all searches of a run share
+struct Shared {
+    started: Instant,
+    nodes: AtomicU64,
+    solutions: AtomicUsize,
+    stop: AtomicBool,
+    stop_reason: Mutex<Option<String>>,
+    table: Option<TranspositionTable>,
+}
+
+impl Shared {
+    fn new(settings: &Settings) -> Self {
+        Shared {
+            table: match settings.transposition_memory {
+                0 => None,
+                megabytes => Some(TranspositionTable::new(megabytes)),
+            },
+            started: Instant::now(),
+            nodes: AtomicU64::new(0),
+            solutions: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+            stop_reason: Mutex::new(None),
+        }
+    }
+
+    /// Stop all searches, the first reason is kept
+    fn stop(&self, reason: String) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.stop_reason.lock().unwrap().get_or_insert(reason);
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// The number of a new solution, None if the limit is reached already
+    ///
+    /// Several threads can find a solution before they see the stop, the
+    /// counter only hands out numbers up to the limit.
+    fn count_solution(&self, limit: usize) -> Option<usize> {
+        self.solutions
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                (limit == 0 || count < limit).then_some(count + 1)
+            })
+            .ok()
+            .map(|count| count + 1)
+    }
+}
+
+/// The state of a depth-first search
+struct Search<'a> {
+    settings: &'a Settings,
+    shared: &'a Shared,
+    occupied: u128,
+    /// bit set of the placed pieces
+    used: u32,
+    printbox: PrintBox,
+    /// placement ids of the placed pieces
+    stack: Vec<usize>,
+    stats: Stats,
+    /// the box of the first placement with `max_depth` pieces
+    deepest: PrintBox,
+    /// collect the placements of this depth instead of searching below
+    split: Option<(usize, Vec<Vec<usize>>)>,
+    /// placement ids of the packings found, if they are collected
+    found: Vec<Vec<usize>>,
+    /// with propagation: the domains of the current placement
+    domains: Option<Domains>,
+    /// trail mark and conflict before each placed piece
+    marks: Vec<(usize, bool)>,
+    /// the propagation of the current placement ran into a conflict
+    conflict: bool,
+}
+
+impl<'a> Search<'a> {
+    fn new(settings: &'a Settings, shared: &'a Shared) -> Self {
+        let mut conflict = false;
+        let domains = settings.propagate.then(|| {
+            let mut domains = Domains::new();
+            conflict = domains.settle().is_err();
+            domains
+        });
+        Search {
+            settings,
+            shared,
+            occupied: 0,
+            used: 0,
+            printbox: PrintBox::new(),
+            stack: Vec::with_capacity(PIECES),
+            stats: Stats::default(),
+            deepest: PrintBox::new(),
+            split: None,
+            found: Vec::new(),
+            domains,
+            marks: Vec::with_capacity(PIECES),
+            conflict,
+        }
+    }
+
+    /// Bit set of the pieces that may be placed next
+    ///
+    /// These are the unused anchored pieces and the unused free piece with
+    /// the lowest name.
+    fn allowed(&self) -> u32 {
+        let unused = !self.used & ((1 << PIECES) - 1);
+        let free = (0..PIECES)
+            .filter(|name| is_free(*name))
+            .fold(0u32, |free, name| free | 1 << name);
+        let anchored = unused & !free;
+        let next_free = unused & free;
+        anchored | (next_free & next_free.wrapping_neg())
+    }
+
+    /// The placement of an allowed piece does not overlap and is still possible
+    fn fits(&self, allowed: u32, id: usize) -> bool {
+        let entry = placements::entry(id);
+        allowed & 1 << entry.name != 0
+            && self.occupied & entry.mask == 0
+            && self.domains.as_ref().is_none_or(|d| d.is_alive(id))
+    }
+
+    /// The empty cell with the fewest fitting placements and these placements
+    ///
+    /// None if the box is full.
+    fn most_constrained(&self) -> Option<Vec<usize>> {
+        let allowed = self.allowed();
+        let mut best: Option<(usize, usize)> = None;
+        let mut empty = FULL & !self.occupied;
+        while empty != 0 {
+            let cell = empty.trailing_zeros() as usize;
+            empty &= empty - 1;
+            // counting beyond the fewest so far does not change the choice
+            let count = placements::covering(cell)
+                .iter()
+                .filter(|id| self.fits(allowed, **id))
+                .take(best.map_or(usize::MAX, |(_, fewest)| fewest))
+                .count();
+            if best.is_none_or(|(_, fewest)| count < fewest) {
+                best = Some((cell, count));
+                if count == 0 {
+                    break;
+                }
+            }
+        }
+        best.map(|(cell, _)| {
+            placements::covering(cell)
+                .iter()
+                .copied()
+                .filter(|id| self.fits(allowed, *id))
+                .collect()
+        })
+    }
+
+    /// With pruning: the empty cells cannot be filled by the unused pieces
+    fn dead_region(&self) -> bool {
+        self.settings.prune && !prune::can_fill(FULL & !self.occupied, !self.used)
+    }
+
+    fn place(&mut self, id: usize) {
+        let entry = placements::entry(id);
+        self.occupied |= entry.mask;
+        self.used |= 1 << entry.name;
+        for [x, y, z] in &entry.cells {
+            self.printbox
+                .add(*x as usize, *y as usize, *z as usize, entry.name);
+        }
+        self.stack.push(id);
+        if let Some(domains) = &mut self.domains {
+            self.marks.push((domains.mark(), self.conflict));
+            if !self.conflict {
+                self.conflict = domains.assign(id).is_err();
+            }
+        }
+    }
+
+    fn unplace(&mut self) {
+        let id = self.stack.pop().unwrap();
+        let entry = placements::entry(id);
+        self.occupied &= !entry.mask;
+        self.used &= !(1 << entry.name);
+        for [x, y, z] in &entry.cells {
+            self.printbox
+                .remove(*x as usize, *y as usize, *z as usize, entry.name);
+        }
+        if let Some(domains) = &mut self.domains {
+            let (mark, conflict) = self.marks.pop().unwrap();
+            domains.undo(mark);
+            self.conflict = conflict;
+        }
+    }
+
+    /// Stop all searches if a limit is reached
+    fn limit_reached(&mut self) -> bool {
+        if self.shared.stopped() {
+            return true;
+        }
+        let nodes = self.shared.nodes.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(reason) = self.settings.limits.nodes_exceeded(nodes) {
+            self.shared.stop(reason);
+            return true;
+        }
+        if nodes.is_multiple_of(REPORT_INTERVAL) {
+            println!(
+                "Step: nodes: {}, depth: {}, max depth: {}, dead ends: {}, pruned: {}, \
+                 transpositions: {}, conflicts: {}",
+                nodes,
+                self.stack.len(),
+                self.stats.max_depth,
+                self.stats.dead_ends,
+                self.stats.pruned,
+                self.stats.transpositions,
+                self.stats.conflicts
+            );
+        }
+        if nodes.is_multiple_of(CHECK_INTERVAL) {
+            if let Some(reason) = self.settings.limits.time_exceeded(self.shared.started) {
+                self.shared.stop(reason);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Visit the node of the current placement, true if the search is to stop
+    fn search(&mut self) -> bool {
+        if self.limit_reached() {
+            return true;
+        }
+        self.stats.nodes += 1;
+        if self.stack.len() > self.stats.max_depth {
+            self.stats.max_depth = self.stack.len();
+            self.deepest = self.printbox.clone();
+        }
+        if self.dead_region() {
+            self.stats.pruned += 1;
+            return false;
+        }
+        if self.conflict {
+            self.stats.conflicts += 1;
+            return false;
+        }
+        if let Some((depth, units)) = &mut self.split {
+            if self.stack.len() == *depth {
+                units.push(self.stack.clone());
+                return false;
+            }
+        }
+        let table = match &self.shared.table {
+            None => return self.expand(),
+            Some(table) => table,
+        };
+        if table.is_dead(self.occupied, self.used) {
+            self.stats.transpositions += 1;
+            return false;
+        }
+        let solutions = self.stats.solutions;
+        let stop = self.expand();
+        // a splitting search does not search below the work units
+        if !stop && self.stats.solutions == solutions && self.split.is_none() {
+            table.store(self.occupied, self.used);
+        }
+        stop
+    }
+
+    /// Search the subtree of a work unit, its root was visited on splitting
+    fn search_unit(&mut self, unit: &[usize]) -> bool {
+        for id in unit {
+            self.place(*id);
+        }
+        if self.conflict {
+            self.stats.conflicts += 1;
+            return false;
+        }
+        self.expand()
+    }
+
+    /// Search below the visited node of the current placement
+    fn expand(&mut self) -> bool {
+        let candidates = match self.most_constrained() {
+            Some(candidates) => candidates,
+            None => {
+                let number = match self.shared.count_solution(self.settings.solutions) {
+                    Some(number) => number,
+                    None => return true,
+                };
+                self.stats.solutions += 1;
+                if self.settings.collect {
+                    self.found.push(self.stack.clone());
+                }
+                println!("Solution #{}: {}", number, self.printbox);
+                if number == self.settings.solutions {
+                    self.shared.stop(format!("{} solutions found", number));
+                    return true;
+                }
+                return false;
+            }
+        };
+        if candidates.is_empty() {
+            self.stats.dead_ends += 1;
+            return false;
+        }
+        for id in candidates {
+            self.place(id);
+            let stop = self.search();
+            self.unplace();
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+pub use self::estimate::estimate;
+pub use self::units::{merge, run_unit, write_units};
+
+/// Search packings of the whole box and print them with the statistics
+pub fn solve(settings: &Settings) -> Stats {
+    let shared = Shared::new(settings);
+    let (stats, deepest) = match settings.threads {
+        None => {
+            let mut search = Search::new(settings, &shared);
+            search.search();
+            (search.stats, search.deepest)
+        }
+        Some(threads) => parallel::solve(settings, &shared, threads),
+    };
+    let stop_reason = shared
+        .stop_reason
+        .into_inner()
+        .unwrap()
+        .unwrap_or_else(|| "search space exhausted".to_string());
+    println!("Stop criterion: {}", stop_reason);
+    if let Some(table) = &shared.table {
+        table.report();
+    }
+    println!(
+        "Search: nodes: {}, max depth: {}, dead ends: {}, pruned: {}, transpositions: {}, \
+         conflicts: {}, solutions (w, x, y in name order): {}, runtime: {:.3}s",
+        stats.nodes,
+        stats.max_depth,
+        stats.dead_ends,
+        stats.pruned,
+        stats.transpositions,
+        stats.conflicts,
+        stats.solutions,
+        shared.started.elapsed().as_secs_f64()
+    );
+    if stats.solutions == 0 {
+        println!(
+            "Deepest placement with {} pieces: {}",
+            stats.max_depth, deepest
+        );
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_and_unplace() {
+        let settings = Settings::default();
+        let shared = Shared::new(&settings);
+        let mut search = Search::new(&settings, &shared);
+        let id = placements::range(0).start;
+        search.place(id);
+        assert_eq!(search.occupied, placements::entry(id).mask);
+        let allowed = search.allowed();
+        assert!(!search.fits(allowed, id));
+        // 'x' waits for 'w'
+        assert_ne!(allowed & 1 << 22, 0);
+        assert_eq!(allowed & 1 << 23, 0);
+        search.unplace();
+        assert_eq!(search.occupied, 0);
+        assert_eq!(search.printbox, PrintBox::new());
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let settings = Settings {
+            limits: Limits {
+                nodes: Some(100),
+                seconds: None,
+            },
+            ..Settings::default()
+        };
+        let stats = solve(&settings);
+        assert_eq!(stats.nodes, 100);
+        assert!(stats.max_depth > 0);
+    }
+
+    #[test]
+    fn test_solution_limit() {
+        let settings = Settings {
+            solutions: 2,
+            ..Settings::default()
+        };
+        let shared = Shared::new(&settings);
+        assert_eq!(shared.count_solution(2), Some(1));
+        assert_eq!(shared.count_solution(2), Some(2));
+        assert_eq!(shared.count_solution(2), None);
+        assert_eq!(shared.count_solution(0), Some(3));
+    }
+
+    #[test]
+    fn test_propagation_finds_the_same_solutions() {
+        // pin the first pieces of a descent that propagation does not refute
+        let settings = Settings {
+            propagate: true,
+            ..Settings::default()
+        };
+        let shared = Shared::new(&settings);
+        let mut search = Search::new(&settings, &shared);
+        'descend: while let Some(candidates) = search.most_constrained() {
+            for id in candidates {
+                search.place(id);
+                if !search.conflict {
+                    continue 'descend;
+                }
+                search.unplace();
+            }
+            break;
+        }
+        assert!(search.stack.len() > 7);
+        let pins = &search.stack[..7];
+        let searches: Vec<(Vec<Vec<usize>>, Stats)> = [false, true]
+            .iter()
+            .map(|propagate| {
+                let settings = Settings {
+                    solutions: 0,
+                    propagate: *propagate,
+                    collect: true,
+                    ..Settings::default()
+                };
+                let shared = Shared::new(&settings);
+                let mut search = Search::new(&settings, &shared);
+                assert!(!search.search_unit(pins));
+                assert_eq!(*shared.stop_reason.lock().unwrap(), None);
+                let mut found = search.found;
+                found.sort();
+                (found, search.stats)
+            })
+            .collect();
+        let (plain, propagated) = (&searches[0], &searches[1]);
+        // the subtree may hold no packing, then both have to agree on that
+        assert_eq!(plain.0, propagated.0);
+        assert_eq!(plain.1.solutions, propagated.1.solutions);
+        assert!(propagated.1.conflicts > 0);
+        assert!(propagated.1.nodes < plain.1.nodes);
+    }
+
+    #[test]
+    fn test_propagation() {
+        let settings = Settings {
+            limits: Limits {
+                nodes: Some(2000),
+                seconds: None,
+            },
+            propagate: true,
+            ..Settings::default()
+        };
+        let stats = solve(&settings);
+        assert_eq!(stats.nodes, 2000);
+        assert!(stats.conflicts > 0);
+    }
+}
@@ -0,0 +1,168 @@
+//! Size of the backtracking tree estimated by Knuth's Monte Carlo method
+//!
+//! A probe walks from the root to a leaf of the tree the backtracker
+//! searches and takes a random child at every node. With the branching
+//! factors d1, d2, ... along the path, 1 + d1 + d1 d2 + ... is an unbiased
+//! estimate of the number of nodes and the product of all factors one of
+//! the number of leaves of the kind the probe ended in. The mean over
+//! many probes comes with a confidence interval from their spread.
+
+use std::time::Instant;
+
+use genevo::random::{random_index, Prng, Rng, SeedableRng};
+
+use crate::limits::Limits;
+
+use super::{Search, Settings, Shared};
+
+/// z value of the 95% confidence intervals
+const Z_95: f64 = 1.96;
+/// Nodes of the search that measures the nodes per second
+const CALIBRATION_NODES: u64 = 200_000;
+
+/// How a probe ended
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Leaf {
+    Solution,
+    DeadEnd,
+    Pruned,
+}
+
+/// The path of one probe
+#[derive(Clone, Debug, PartialEq)]
+struct Probe {
+    /// number of children of the inner nodes
+    branchings: Vec<usize>,
+    leaf: Leaf,
+}
+
+impl Probe {
+    /// The estimates of the number of nodes and the number of leaves
+    fn estimate(&self) -> (f64, f64) {
+        let mut nodes = 1.0;
+        let mut weight = 1.0;
+        for branching in &self.branchings {
+            weight *= *branching as f64;
+            nodes += weight;
+        }
+        (nodes, weight)
+    }
+}
+
+/// Walk down a random path of the tree
+fn probe<R: Rng>(settings: &Settings, shared: &Shared, rng: &mut R) -> Probe {
+    let mut search = Search::new(settings, shared);
+    let mut branchings = Vec::new();
+    let leaf = loop {
+        if search.dead_region() || search.conflict {
+            break Leaf::Pruned;
+        }
+        match search.most_constrained() {
+            None => break Leaf::Solution,
+            Some(candidates) if candidates.is_empty() => break Leaf::DeadEnd,
+            Some(candidates) => {
+                branchings.push(candidates.len());
+                search.place(candidates[random_index(rng, candidates.len())]);
+            }
+        }
+    };
+    Probe { branchings, leaf }
+}
+
+/// Mean and half width of the 95% confidence interval
+fn mean_interval(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n.max(1.0);
+    if values.len() < 2 {
+        return (mean, f64::INFINITY);
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, Z_95 * (variance / n).sqrt())
+}
+
+/// Nodes per second of the backtracker
+fn measure_rate(settings: &Settings) -> f64 {
+    let settings = Settings {
+        solutions: 0,
+        limits: Limits {
+            nodes: Some(CALIBRATION_NODES),
+            seconds: None,
+        },
+        ..settings.clone()
+    };
+    let shared = Shared::new(&settings);
+    let mut search = Search::new(&settings, &shared);
+    search.search();
+    search.stats.nodes as f64 / shared.started.elapsed().as_secs_f64().max(1e-9)
+}
+
+/// Estimate the tree size with `probes` probes and print it
+pub fn estimate(settings: &Settings, probes: usize, seed: u64) {
+    let shared = Shared::new(settings);
+    let mut rng = Prng::seed_from_u64(seed);
+    let started = Instant::now();
+    let mut nodes = Vec::with_capacity(probes);
+    let mut leaves = Vec::with_capacity(probes);
+    let mut solutions = Vec::with_capacity(probes);
+    let mut depths = 0;
+    for _ in 0..probes {
+        let probe = probe(settings, &shared, &mut rng);
+        let (node_estimate, leaf_estimate) = probe.estimate();
+        depths += probe.branchings.len();
+        nodes.push(node_estimate);
+        leaves.push(leaf_estimate);
+        solutions.push(if probe.leaf == Leaf::Solution {
+            leaf_estimate
+        } else {
+            0.0
+        });
+    }
+    let seconds = started.elapsed().as_secs_f64();
+    let rate = measure_rate(settings);
+    let interval = |values: &[f64], factor: f64| {
+        let (mean, half) = mean_interval(values);
+        format!("{:.3e} ± {:.3e}", mean * factor, half * factor)
+    };
+    println!(
+        "Estimate from {} probes with mean depth {:.1} in {:.3}s",
+        probes,
+        depths as f64 / probes.max(1) as f64,
+        seconds
+    );
+    println!("Nodes: {}", interval(&nodes, 1.0));
+    println!("Leaves: {}", interval(&leaves, 1.0));
+    println!("Solutions: {}", interval(&solutions, 1.0));
+    println!(
+        "Runtime at {:.0} nodes per second: {} seconds, {} days",
+        rate,
+        interval(&nodes, 1.0 / rate),
+        interval(&nodes, 1.0 / rate / 86400.0)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_estimate() {
+        let path = Probe {
+            branchings: vec![2, 3],
+            leaf: Leaf::DeadEnd,
+        };
+        assert_eq!(path.estimate(), (9.0, 6.0));
+        let settings = Settings::default();
+        let shared = Shared::new(&settings);
+        let path = probe(&settings, &shared, &mut Prng::seed_from_u64(1));
+        assert!(!path.branchings.is_empty());
+        assert!(path.branchings.iter().all(|b| *b > 0));
+    }
+
+    #[test]
+    fn test_mean_interval() {
+        let (mean, half) = mean_interval(&[1.0, 2.0, 3.0]);
+        assert_eq!(mean, 2.0);
+        assert!((half - Z_95 / 3f64.sqrt()).abs() < 1e-12);
+        assert_eq!(mean_interval(&[5.0]).1, f64::INFINITY);
+    }
+}
@@ -0,0 +1,114 @@
+//! Parallel search of the subtrees below the first decisions
+//!
+//! A first search stops at the split depth and collects the placements
+//! there as work units. The units are independent, a rayon pool hands
+//! them out one by one and idle threads steal the units that are left,
+//! so a thread that got small subtrees takes over more units. Solutions
+//! are printed when they are found, counters are kept per thread.
+
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+
+use crate::cube::PrintBox;
+
+use super::{Search, Settings, Shared, Stats};
+
+/// The counters of one thread
+#[derive(Clone, Debug, Default)]
+struct ThreadStats {
+    units: usize,
+    stats: Stats,
+}
+
+/// Split the tree into work units
+///
+/// Returns the units and the search that collected them.
+pub(super) fn split<'a>(
+    settings: &'a Settings,
+    shared: &'a Shared,
+) -> (Vec<Vec<usize>>, Search<'a>) {
+    let mut search = Search::new(settings, shared);
+    search.split = Some((settings.split_depth, Vec::new()));
+    search.search();
+    let (_, units) = search.split.take().unwrap();
+    (units, search)
+}
+
+/// Search the work units on a pool of threads and print the counters per thread
+pub(super) fn solve(settings: &Settings, shared: &Shared, threads: usize) -> (Stats, PrintBox) {
+    let (units, splitter) = split(settings, shared);
+    println!(
+        "Split depth {}: {} work units on {} threads",
+        settings.split_depth,
+        units.len(),
+        threads
+    );
+    let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool,
+        Err(error) => {
+            println!("Cannot start {} threads: {}", threads, error);
+            return (splitter.stats, splitter.deepest);
+        }
+    };
+    let per_thread: Vec<Mutex<ThreadStats>> = (0..pool.current_num_threads())
+        .map(|_| Mutex::new(ThreadStats::default()))
+        .collect();
+    let deepest = Mutex::new((splitter.stats.max_depth, splitter.deepest.clone()));
+    pool.install(|| {
+        units.par_iter().with_max_len(1).for_each(|unit| {
+            if shared.stopped() {
+                return;
+            }
+            let mut search = Search::new(settings, shared);
+            search.search_unit(unit);
+            let mut thread = per_thread[rayon::current_thread_index().unwrap_or(0)]
+                .lock()
+                .unwrap();
+            thread.units += 1;
+            thread.stats.merge(&search.stats);
+            let mut deepest = deepest.lock().unwrap();
+            if search.stats.max_depth > deepest.0 {
+                *deepest = (search.stats.max_depth, search.deepest);
+            }
+        })
+    });
+    let mut stats = splitter.stats;
+    for (index, thread) in per_thread.into_iter().enumerate() {
+        let thread = thread.into_inner().unwrap();
+        println!(
+            "Thread #{}: units: {}, nodes: {}, max depth: {}, dead ends: {}, pruned: {}, \
+             transpositions: {}, conflicts: {}, solutions: {}",
+            index,
+            thread.units,
+            thread.stats.nodes,
+            thread.stats.max_depth,
+            thread.stats.dead_ends,
+            thread.stats.pruned,
+            thread.stats.transpositions,
+            thread.stats.conflicts,
+            thread.stats.solutions
+        );
+        stats.merge(&thread.stats);
+    }
+    (stats, deepest.into_inner().unwrap().1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_covers_the_search() {
+        // the units below depth 1 are the candidates of the most constrained cell
+        let settings = Settings {
+            split_depth: 1,
+            ..Settings::default()
+        };
+        let shared = Shared::new(&settings);
+        let (units, splitter) = split(&settings, &shared);
+        let root = Search::new(&settings, &shared).most_constrained().unwrap();
+        assert_eq!(units.len() + splitter.stats.pruned as usize, root.len());
+        assert!(units.iter().all(|unit| unit.len() == 1));
+    }
+}
@@ -0,0 +1,109 @@
+//! Dead regions: empty cells that no set of pieces can fill
+//!
+//! The empty cells of a box split into connected components, neighbours
+//! share a face. Every piece fills 5 cells of one component, so a partial
+//! packing is doomed if a component has a size that is no multiple of 5.
+//! A component of exactly 5 cells needs an unused piece with a placement
+//! that is the component.
+
+use crate::cube::{CELLS, HEIGHT, WIDTH};
+use crate::piece::SHAPE_POINT;
+use crate::placements;
+
+const FULL: u128 = (1 << CELLS) - 1;
+const X_STRIDE: usize = WIDTH * HEIGHT;
+const Y_STRIDE: usize = HEIGHT;
+
+/// The cells whose coordinate of the axis with the stride is `value`
+const fn plane(stride: usize, value: usize) -> u128 {
+    let mut mask = 0;
+    let mut cell = 0;
+    while cell < CELLS {
+        if cell / stride % 5 == value {
+            mask |= 1 << cell;
+        }
+        cell += 1;
+    }
+    mask
+}
+
+const Z_FIRST: u128 = plane(1, 0);
+const Z_LAST: u128 = plane(1, HEIGHT - 1);
+const Y_FIRST: u128 = plane(Y_STRIDE, 0);
+const Y_LAST: u128 = plane(Y_STRIDE, WIDTH - 1);
+
+/// The cells and their face neighbours
+fn grow(cells: u128) -> u128 {
+    cells
+        | (cells & !Z_LAST) << 1
+        | (cells & !Z_FIRST) >> 1
+        | (cells & !Y_LAST) << Y_STRIDE
+        | (cells & !Y_FIRST) >> Y_STRIDE
+        | (cells << X_STRIDE & FULL)
+        | cells >> X_STRIDE
+}
+
+/// The connected components of a set of cells
+pub fn components(mut cells: u128) -> Vec<u128> {
+    let mut components = Vec::new();
+    while cells != 0 {
+        let mut component = cells & cells.wrapping_neg();
+        loop {
+            let grown = grow(component) & cells;
+            if grown == component {
+                break;
+            }
+            component = grown;
+        }
+        cells &= !component;
+        components.push(component);
+    }
+    components
+}
+
+/// The empty cells may still be filled by the unused pieces
+///
+/// `unused` is the bit set of the pieces that are not placed.
+pub fn can_fill(empty: u128, unused: u32) -> bool {
+    components(empty).into_iter().all(|component| {
+        let size = component.count_ones() as usize;
+        if !size.is_multiple_of(SHAPE_POINT) {
+            return false;
+        }
+        size > SHAPE_POINT
+            || placements::covering(component.trailing_zeros() as usize)
+                .iter()
+                .map(|id| placements::entry(*id))
+                .any(|entry| entry.mask == component && unused & 1 << entry.name != 0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_components() {
+        assert_eq!(components(FULL), vec![FULL]);
+        // the plane x = 2 splits the box into two halves
+        let wall = plane(X_STRIDE, 2);
+        let halves = components(FULL & !wall);
+        assert_eq!(halves.len(), 2);
+        assert_eq!(halves[0].count_ones(), 50);
+        // cells 4 and 5 are no neighbours, they are in different rows
+        assert_eq!(components(1 << 4 | 1 << 5).len(), 2);
+    }
+
+    #[test]
+    fn test_can_fill() {
+        let all: u32 = (1 << 25) - 1;
+        assert!(can_fill(FULL, all));
+        assert!(!can_fill(FULL & !1, all));
+        // a placement of 'a' as the only gap
+        let a = placements::entry(placements::range(0).start).mask;
+        assert!(can_fill(a, 1));
+        assert!(!can_fill(a, 1 << 1));
+        // five cells in a row are no placement of the shape
+        assert!(!can_fill(0b11111, all));
+    }
+}
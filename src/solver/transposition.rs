@@ -0,0 +1,60 @@
+//! Transposition table of partial packings without a solution below
+//!
+//! Placing the same pieces in another order gives the same partial
+//! packing, and the search below a packing only depends on the occupied
+//! cells and the used pieces. Once the subtree of such a state is searched
+//! completely without a solution, the state is stored and the search
+//! skips it when it comes up again. The table is a `BoundedMap` sized by
+//! its memory.
+
+use crate::bounded::BoundedMap;
+
+/// Approximate bytes of a stored state, key and hash map overhead
+const ENTRY_BYTES: usize = 48;
+
+/// Occupied cells and used pieces
+type State = (u128, u32);
+
+/// Bounded set of dead states with hit statistics
+#[derive(Debug)]
+pub struct TranspositionTable(BoundedMap<State, ()>);
+
+impl TranspositionTable {
+    /// A table that uses at most about `megabytes` MB
+    pub fn new(megabytes: usize) -> Self {
+        TranspositionTable(BoundedMap::new(megabytes * (1 << 20) / ENTRY_BYTES))
+    }
+
+    /// The state is known to have no solution below
+    pub fn is_dead(&self, occupied: u128, used: u32) -> bool {
+        self.0.get(&(occupied, used)).is_some()
+    }
+
+    /// Store a state whose subtree has no solution
+    pub fn store(&self, occupied: u128, used: u32) {
+        self.0.insert((occupied, used), ());
+    }
+
+    pub fn report(&self) {
+        self.0.report("Transposition table", "states");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_bound() {
+        let table = TranspositionTable::new(1);
+        assert!(!table.is_dead(0b111, 1));
+        table.store(0b111, 1);
+        assert!(table.is_dead(0b111, 1));
+        assert!(!table.is_dead(0b111, 2));
+        assert_eq!((table.0.hits(), table.0.lookups()), (1, 3));
+        for occupied in 0..2 * table.0.capacity() as u128 {
+            table.store(occupied, 0);
+        }
+        assert!(table.0.len() <= table.0.capacity());
+    }
+}
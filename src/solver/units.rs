@@ -0,0 +1,256 @@
+//! Work unit files to spread a search over several machines
+//!
+//! `write_units` splits the tree like the threads do and writes the units
+//! round robin into numbered files, one unit per line. `run_unit` searches
+//! all units of a file and writes a result file with the counters and the
+//! packings found, `merge` sums up result files. Placements are written as
+//! `name:combination` like the pins of `ge`, e.g. `a:0,h:4195`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::{i2c, placements};
+
+use super::parallel::split;
+use super::{Search, Settings, Shared, Stats};
+
+/// Placement ids as `name:combination` list
+fn encode(ids: &[usize]) -> String {
+    ids.iter()
+        .map(|id| {
+            let entry = placements::entry(*id);
+            format!("{}:{}", i2c(entry.name as isize), entry.combination)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The placement ids of a `name:combination` list
+fn decode(line: &str) -> Result<Vec<usize>, String> {
+    line.split(',').map(placements::parse).collect()
+}
+
+/// Non empty lines without comments
+fn content_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Write the work units of the split depth into `files` files in the directory
+///
+/// One file per unit if `files` is not set. Returns the number of files.
+pub fn write_units(
+    settings: &Settings,
+    directory: &str,
+    files: Option<usize>,
+) -> Result<usize, String> {
+    let shared = Shared::new(settings);
+    let (units, _) = split(settings, &shared);
+    let files = files.unwrap_or(units.len()).clamp(1, units.len().max(1));
+    let mut contents: Vec<String> = (1..=files)
+        .map(|number| {
+            format!(
+                "# work unit file {} of {}, split depth {}\n",
+                number, files, settings.split_depth
+            )
+        })
+        .collect();
+    for (index, unit) in units.iter().enumerate() {
+        contents[index % files].push_str(&format!("{}\n", encode(unit)));
+    }
+    fs::create_dir_all(directory).map_err(|e| format!("{}: {}", directory, e))?;
+    for (index, content) in contents.iter().enumerate() {
+        let file = Path::new(directory).join(format!("unit-{:04}.txt", index + 1));
+        fs::write(&file, content).map_err(|e| format!("{}: {}", file.display(), e))?;
+    }
+    println!(
+        "{} work units of depth {} written to {} files in {}",
+        units.len(),
+        settings.split_depth,
+        files,
+        directory
+    );
+    Ok(files)
+}
+
+/// Search all units of a work unit file and write the result file
+pub fn run_unit(settings: &Settings, file: &str, output: &str) -> Result<Stats, String> {
+    let content = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+    let units: Vec<Vec<usize>> = content_lines(&content)
+        .map(decode)
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{}: {}", file, e))?;
+    let settings = &Settings {
+        collect: true,
+        ..settings.clone()
+    };
+    let shared = Shared::new(settings);
+    let mut stats = Stats::default();
+    let mut found = Vec::new();
+    for unit in &units {
+        let mut search = Search::new(settings, &shared);
+        let stop = search.search_unit(unit);
+        stats.merge(&search.stats);
+        found.extend(search.found);
+        if stop {
+            break;
+        }
+    }
+    let stop_reason = shared.stop_reason.into_inner().unwrap();
+    let mut result = format!("# result of {}\n", file);
+    result.push_str(&format!("units: {}\n", units.len()));
+    result.push_str(&format!("complete: {}\n", stop_reason.is_none()));
+    result.push_str(&format!("nodes: {}\n", stats.nodes));
+    result.push_str(&format!("max_depth: {}\n", stats.max_depth));
+    result.push_str(&format!("dead_ends: {}\n", stats.dead_ends));
+    result.push_str(&format!("pruned: {}\n", stats.pruned));
+    result.push_str(&format!("conflicts: {}\n", stats.conflicts));
+    for solution in &found {
+        result.push_str(&format!("solution: {}\n", encode(solution)));
+    }
+    fs::write(output, result).map_err(|e| format!("{}: {}", output, e))?;
+    println!(
+        "Unit file {}: {} units, nodes: {}, solutions: {}{}, result saved to {}",
+        file,
+        units.len(),
+        stats.nodes,
+        stats.solutions,
+        stop_reason.map_or(String::new(), |r| format!(", incomplete: {}", r)),
+        output
+    );
+    Ok(stats)
+}
+
+/// The totals of result files
+#[derive(Debug, Default, PartialEq)]
+struct Totals {
+    results: usize,
+    units: usize,
+    incomplete: usize,
+    stats: Stats,
+    /// the packings, the pieces in the order of their names
+    solutions: BTreeSet<Vec<usize>>,
+}
+
+impl Totals {
+    fn add(&mut self, content: &str) -> Result<(), String> {
+        self.results += 1;
+        for line in content_lines(content) {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("{} is no key: value", line))?;
+            let value = value.trim();
+            let count = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| format!("{} is no count of {}", value, key))
+            };
+            match key {
+                "units" => self.units += count()? as usize,
+                "complete" => {
+                    if value != "true" {
+                        self.incomplete += 1;
+                    }
+                }
+                "nodes" => self.stats.nodes += count()?,
+                "max_depth" => self.stats.max_depth = self.stats.max_depth.max(count()? as usize),
+                "dead_ends" => self.stats.dead_ends += count()?,
+                "pruned" => self.stats.pruned += count()?,
+                "conflicts" => self.stats.conflicts += count()?,
+                "solution" => {
+                    let mut solution = decode(value)?;
+                    solution.sort_unstable();
+                    self.stats.solutions += 1;
+                    self.solutions.insert(solution);
+                }
+                _ => return Err(format!("unknown key {}", key)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sum up result files, print the totals and save or print the distinct packings
+pub fn merge(files: &[&str], output: Option<&str>) -> Result<(), String> {
+    let mut totals = Totals::default();
+    for file in files {
+        let content = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+        totals
+            .add(&content)
+            .map_err(|e| format!("{}: {}", file, e))?;
+    }
+    println!(
+        "Merged {} results of {} units, incomplete: {}, nodes: {}, max depth: {}, \
+         dead ends: {}, pruned: {}, conflicts: {}, solutions: {}, distinct solutions \
+         (w, x, y in name order): {}",
+        totals.results,
+        totals.units,
+        totals.incomplete,
+        totals.stats.nodes,
+        totals.stats.max_depth,
+        totals.stats.dead_ends,
+        totals.stats.pruned,
+        totals.stats.conflicts,
+        totals.stats.solutions,
+        totals.solutions.len()
+    );
+    let list: String = totals
+        .solutions
+        .iter()
+        .map(|solution| format!("{}\n", encode(solution)))
+        .collect();
+    match output {
+        None => print!("{}", list),
+        Some(file) => {
+            fs::write(file, list).map_err(|e| format!("{}: {}", file, e))?;
+            println!(
+                "{} distinct solutions saved to {}",
+                totals.solutions.len(),
+                file
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let ids = vec![placements::range(0).start, placements::id(7, 4195).unwrap()];
+        assert_eq!(encode(&ids), "a:0,h:4195");
+        assert_eq!(decode("a:0, h:4195").unwrap(), ids);
+        assert!(decode("a:1").is_err());
+        assert!(decode("z:0").is_err());
+    }
+
+    #[test]
+    fn test_merge_deduplicates() {
+        let solution = encode(&[placements::range(0).start, placements::range(24).start]);
+        let swapped = encode(&[placements::range(24).start, placements::range(0).start]);
+        let mut totals = Totals::default();
+        totals
+            .add(&format!(
+                "units: 2\ncomplete: true\nnodes: 10\nsolution: {}\n",
+                solution
+            ))
+            .unwrap();
+        totals
+            .add(&format!(
+                "# result\nunits: 1\ncomplete: false\nnodes: 5\nsolution: {}\n",
+                swapped
+            ))
+            .unwrap();
+        assert_eq!(totals.units, 3);
+        assert_eq!(totals.incomplete, 1);
+        assert_eq!(totals.stats.nodes, 15);
+        assert_eq!(totals.stats.solutions, 2);
+        assert_eq!(totals.solutions.len(), 1);
+        assert!(totals.add("colour: blue").is_err());
+    }
+}